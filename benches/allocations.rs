@@ -0,0 +1,64 @@
+//! Counts the heap allocations of a single verification, for comparing
+//! `pss::verify` with `pss::verify_with_scratch`.
+//!
+//! Kept apart from the timing benchmarks, as the counting allocator would
+//! slow down every allocation they make. Run with
+//! `cargo bench --bench allocations`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rsa::{pss, RSAPrivateKey};
+use sha2::{Digest, Sha256};
+
+/// Counts heap allocations.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Returns how many allocations a single call of `f` makes.
+fn count_allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let priv_key = RSAPrivateKey::new(&mut rng, 2048).unwrap();
+    let pub_key = priv_key.to_public_key();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let sig =
+        pss::sign::<_, StdRng, _>(&mut rng, None, &priv_key, &digest, None, &mut Sha256::new())
+            .unwrap();
+
+    let plain =
+        count_allocations(|| pss::verify(&pub_key, &digest, &sig, &mut Sha256::new()).unwrap());
+    println!("pss::verify: {} allocations per call", plain);
+
+    // The first call grows the buffer to the size of the modulus.
+    let mut scratch = Vec::new();
+    pss::verify_with_scratch::<_, Sha256>(&pub_key, &digest, &sig, &mut scratch).unwrap();
+    let with_scratch = count_allocations(|| {
+        pss::verify_with_scratch::<_, Sha256>(&pub_key, &digest, &sig, &mut scratch).unwrap()
+    });
+    println!(
+        "pss::verify_with_scratch: {} allocations per call",
+        with_scratch
+    );
+}
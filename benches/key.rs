@@ -3,10 +3,11 @@
 extern crate test;
 
 use base64;
-use num_bigint::BigUint;
+use num_bigint::{BigUint, RandBigInt};
 use num_traits::{FromPrimitive, Num};
 use rand::{rngs::StdRng, SeedableRng};
-use rsa::{Hash, PaddingScheme, RSAPrivateKey};
+use rsa::algorithms::KeyGenParams;
+use rsa::{pss, Hash, PaddingScheme, PublicKey, PublicKeyParts, RSAPrivateKey};
 use sha2::{Digest, Sha256};
 use test::Bencher;
 
@@ -55,3 +56,209 @@ fn bench_rsa_2048_pkcsv1_sign_blinded(b: &mut Bencher) {
         test::black_box(res);
     });
 }
+
+#[bench]
+fn bench_rsa_2048_pkcsv1_sign_blinded_pool(b: &mut Bencher) {
+    let mut priv_key = get_key();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    priv_key.enable_blinding_pool(&mut rng, 1);
+
+    b.iter(|| {
+        let res = priv_key
+            .sign_blinded(
+                &mut rng,
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                &digest,
+            )
+            .unwrap();
+        test::black_box(res);
+    });
+}
+
+#[bench]
+fn bench_rsa_2048_pss_verify(b: &mut Bencher) {
+    let priv_key = get_key();
+    let pub_key = priv_key.to_public_key();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let sig =
+        pss::sign::<_, StdRng, _>(&mut rng, None, &priv_key, &digest, None, &mut Sha256::new())
+            .unwrap();
+
+    b.iter(|| test::black_box(pss::verify(&pub_key, &digest, &sig, &mut Sha256::new()).unwrap()));
+}
+
+#[bench]
+fn bench_rsa_2048_pss_verify_with_scratch(b: &mut Bencher) {
+    let priv_key = get_key();
+    let pub_key = priv_key.to_public_key();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let sig =
+        pss::sign::<_, StdRng, _>(&mut rng, None, &priv_key, &digest, None, &mut Sha256::new())
+            .unwrap();
+    let mut scratch = Vec::new();
+
+    b.iter(|| {
+        test::black_box(
+            pss::verify_with_scratch::<_, Sha256>(&pub_key, &digest, &sig, &mut scratch).unwrap(),
+        )
+    });
+}
+
+#[bench]
+fn bench_rsa_2048_pkcsv1_verify_e65537(b: &mut Bencher) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let priv_key = RSAPrivateKey::new(&mut rng, 2048).unwrap();
+    let pub_key = priv_key.to_public_key();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let padding = || PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256));
+    let sig = priv_key.sign(padding(), &digest).unwrap();
+
+    b.iter(|| test::black_box(pub_key.verify(padding(), &digest, &sig).unwrap()));
+}
+
+#[bench]
+fn bench_rsa_2048_pkcsv1_verify_e65537_barrett(b: &mut Bencher) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let priv_key = RSAPrivateKey::new(&mut rng, 2048).unwrap();
+    let mut pub_key = priv_key.to_public_key();
+    pub_key.precompute_barrett();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let padding = || PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256));
+    let sig = priv_key.sign(padding(), &digest).unwrap();
+
+    b.iter(|| test::black_box(pub_key.verify(padding(), &digest, &sig).unwrap()));
+}
+
+/// `pow_mod` falls back to `BigUint::modpow` without Barrett parameters.
+fn bench_pow_mod_e65537(b: &mut Bencher, barrett: bool) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let mut pub_key = RSAPrivateKey::new(&mut rng, 2048).unwrap().to_public_key();
+    if barrett {
+        pub_key.precompute_barrett();
+    }
+    let base = rng.gen_biguint_below(pub_key.n());
+
+    b.iter(|| test::black_box(pub_key.pow_mod(&base, pub_key.e())));
+}
+
+#[bench]
+fn bench_rsa_2048_pow_mod_e65537_modpow(b: &mut Bencher) {
+    bench_pow_mod_e65537(b, false);
+}
+
+#[bench]
+fn bench_rsa_2048_pow_mod_e65537_barrett(b: &mut Bencher) {
+    bench_pow_mod_e65537(b, true);
+}
+
+/// A 4096 bit key with a PS256 signature, whose salt is as long as the hash.
+fn rsa_4096_ps256_signature() -> (rsa::RSAPublicKey, Vec<u8>, Vec<u8>) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let priv_key = RSAPrivateKey::new(&mut rng, 4096).unwrap();
+    let digest = Sha256::digest(b"testing").to_vec();
+    let sig = pss::sign::<_, StdRng, _>(
+        &mut rng,
+        None,
+        &priv_key,
+        &digest,
+        Some(32),
+        &mut Sha256::new(),
+    )
+    .unwrap();
+    (priv_key.to_public_key(), digest, sig)
+}
+
+#[bench]
+fn bench_rsa_4096_pss_verify_recover_salt_len(b: &mut Bencher) {
+    let (pub_key, digest, sig) = rsa_4096_ps256_signature();
+    b.iter(|| {
+        let s_len = pss::verify_pss_ex::<_, Sha256>(&pub_key, &digest, &sig, pss::SaltPolicy::Any);
+        test::black_box(s_len.unwrap())
+    });
+}
+
+#[bench]
+fn bench_rsa_4096_pss_verify_known_salt_len(b: &mut Bencher) {
+    let (pub_key, digest, sig) = rsa_4096_ps256_signature();
+    b.iter(|| {
+        let policy = pss::SaltPolicy::Exactly(32);
+        let s_len = pss::verify_pss_ex::<_, Sha256>(&pub_key, &digest, &sig, policy);
+        test::black_box(s_len.unwrap())
+    });
+}
+
+/// Generates the same key in every iteration, the trial division bound only
+/// changes how many candidates reach Miller-Rabin, not which prime is found.
+fn bench_keygen_trial_division(b: &mut Bencher, trial_division_bound: usize) {
+    let params = KeyGenParams {
+        trial_division_bound,
+        ..KeyGenParams::default()
+    };
+    b.iter(|| {
+        let mut rng = StdRng::seed_from_u64(0);
+        test::black_box(RSAPrivateKey::new_with_params(&mut rng, 2048, &params).unwrap())
+    });
+}
+
+#[bench]
+fn bench_rsa_2048_keygen_trial_division_0(b: &mut Bencher) {
+    bench_keygen_trial_division(b, 0);
+}
+
+#[bench]
+fn bench_rsa_2048_keygen_trial_division_64(b: &mut Bencher) {
+    bench_keygen_trial_division(b, 64);
+}
+
+#[bench]
+fn bench_rsa_2048_keygen_trial_division_256(b: &mut Bencher) {
+    bench_keygen_trial_division(b, 256);
+}
+
+#[bench]
+fn bench_rsa_2048_keygen_trial_division_2048(b: &mut Bencher) {
+    bench_keygen_trial_division(b, 2048);
+}
+
+#[bench]
+fn bench_rsa_2048_keygen_trial_division_8192(b: &mut Bencher) {
+    bench_keygen_trial_division(b, 8192);
+}
+
+/// Sixteen digests, signed one by one or as a batch with an `e = 65537` key.
+fn pss_batch_digests() -> Vec<Vec<u8>> {
+    (0..16u8).map(|i| Sha256::digest(&[i]).to_vec()).collect()
+}
+
+#[bench]
+fn bench_rsa_2048_pss_sign_16_in_loop(b: &mut Bencher) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let priv_key = RSAPrivateKey::new(&mut rng, 2048).unwrap();
+    let digests = pss_batch_digests();
+
+    b.iter(|| {
+        for digest in &digests {
+            let padding = PaddingScheme::new_pss_with_salt::<Sha256, _>(rng.clone(), 32);
+            test::black_box(priv_key.sign_blinded(&mut rng, padding, digest).unwrap());
+        }
+    });
+}
+
+#[bench]
+fn bench_rsa_2048_pss_sign_16_batch(b: &mut Bencher) {
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let priv_key = RSAPrivateKey::new(&mut rng, 2048).unwrap();
+    let digests = pss_batch_digests();
+    let hashes: Vec<&[u8]> = digests.iter().map(Vec::as_slice).collect();
+
+    b.iter(|| {
+        test::black_box(
+            priv_key
+                .sign_pss_batch::<Sha256, _>(&mut rng, &hashes, 32)
+                .unwrap(),
+        )
+    });
+}
@@ -0,0 +1,31 @@
+//! Feeds arbitrary documents into the JSON parser of `rsa::jwks`, which must
+//! return an error for malformed input, never panic or recurse without
+//! bound.
+//!
+//! The input is parsed as a key set, and, base64url encoded, as the
+//! protected header of a JWS checked against a set holding
+//! `test_util::test_key_1024`.
+//!
+//! Run with `cargo fuzz run jwks` from the root of the repository.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsa::jwks::Jwks;
+use rsa::test_util::test_key_1024;
+use rsa::PublicKeyParts;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(json) = core::str::from_utf8(data) {
+        let _ = Jwks::from_json(json);
+    }
+
+    let key = test_key_1024();
+    let set = format!(
+        r#"{{"keys": [{{"kty": "RSA", "n": "{}", "e": "{}"}}]}}"#,
+        base64::encode_config(&key.n().to_bytes_be(), base64::URL_SAFE_NO_PAD),
+        base64::encode_config(&key.e().to_bytes_be(), base64::URL_SAFE_NO_PAD),
+    );
+    let jwks = Jwks::from_json(&set).unwrap();
+    let header = base64::encode_config(data, base64::URL_SAFE_NO_PAD);
+    let _ = jwks.verify_jws(&header, "e30", &[0u8; 128]);
+});
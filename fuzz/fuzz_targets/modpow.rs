@@ -0,0 +1,43 @@
+//! Compares the modular exponentiations of `rsa` against `BigUint::modpow`.
+//!
+//! The input is the length of the modulus and of the exponent in bytes,
+//! followed by the modulus, the exponent and the base, all big endian.
+//!
+//! Run with `cargo fuzz run modpow` from the root of the repository.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use rsa::internals::{ModPow, NumBigint};
+use rsa::RSAPublicKey;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let (n_len, e_len) = (data[0] as usize, data[1] as usize);
+    let data = &data[2..];
+    if data.len() < n_len + e_len {
+        return;
+    }
+    let (modulus, data) = data.split_at(n_len);
+    let (exp, base) = data.split_at(e_len);
+    let modulus = BigUint::from_bytes_be(modulus);
+    let exp = BigUint::from_bytes_be(exp);
+    let base = BigUint::from_bytes_be(base);
+    if modulus.bits() == 0 {
+        return;
+    }
+
+    let expected = base.modpow(&exp, &modulus);
+    assert_eq!(NumBigint.modpow(&base, &exp, &modulus), expected);
+    assert_eq!(NumBigint.modpow_public(&base, &exp, &modulus), expected);
+
+    // The same through a public key, with and without Barrett reduction.
+    let e = BigUint::from(65537u32);
+    if let Ok(mut key) = RSAPublicKey::new(modulus, e) {
+        assert_eq!(key.pow_mod(&base, &exp), expected);
+        key.precompute_barrett();
+        assert_eq!(key.pow_mod(&base, &exp), expected);
+    }
+});
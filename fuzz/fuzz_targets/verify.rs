@@ -0,0 +1,32 @@
+//! Feeds arbitrary signatures and hashes into the RSASSA-PSS verification
+//! functions, which must return an error for malformed input, never panic.
+//!
+//! The first input byte is the length of the hash, the rest of that many
+//! bytes is the hash and everything after it the signature. The key is
+//! fixed, `test_util::test_key_1024`.
+//!
+//! Run with `cargo fuzz run verify` from the root of the repository.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use rsa::pss::{self, SaltPolicy};
+use rsa::test_util::test_key_1024;
+use rsa::RSAPublicKey;
+use sha2::{Digest, Sha256};
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let hashed_len = core::cmp::min(data[0] as usize, data.len() - 1);
+    let (hashed, sig) = data[1..].split_at(hashed_len);
+    let pub_key = RSAPublicKey::from(test_key_1024());
+
+    let _ = pss::verify(&pub_key, hashed, sig, &mut Sha256::new());
+    let _ = pss::verify_pss_ex::<_, Sha256>(&pub_key, hashed, sig, SaltPolicy::Exactly(32));
+    let _ = pss::verify_pss_compat::<_, Sha256>(&pub_key, hashed, sig);
+    let _ = pss::verify_pss_full::<_, Sha256>(&pub_key, hashed, sig);
+    let _ = pss::verify_with_scratch::<_, Sha256>(&pub_key, hashed, sig, &mut Vec::new());
+    let _ = pss::verify_int::<_, Sha256>(&pub_key, hashed, &BigUint::from_bytes_be(sig));
+});
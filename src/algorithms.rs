@@ -1,17 +1,73 @@
 use digest::DynDigest;
 use num_bigint::traits::ModInverse;
-use num_bigint::{BigUint, RandPrime};
-use num_traits::{FromPrimitive, One, Zero};
+use num_bigint::prime::probably_prime;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, One, ToPrimitive, Zero};
 #[allow(unused_imports)]
 use num_traits::Float;
 use rand::Rng;
+use zeroize::Zeroize;
 use alloc::vec;
+use alloc::vec::Vec;
 
 use crate::errors::{Error, Result};
 use crate::key::RSAPrivateKey;
 
 /// Default exponent for RSA keys.
-const EXP: u64 = 65537;
+pub(crate) const EXP: u64 = 65537;
+
+/// Miller-Rabin rounds run on each prime candidate that survives trial
+/// division, followed by a Lucas test.
+const PRIME_CANDIDATE_ROUNDS: usize = 20;
+
+/// How far past a random starting point the search for a number coprime to
+/// the primes up to 53 goes.
+const MAX_PRIME_SEARCH_DELTA: u64 = 1 << 20;
+
+/// Tunables of key generation, see `generate_multi_prime_key_with_params`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyGenParams {
+    /// Number of odd primes prime candidates are divided by before the first
+    /// Miller-Rabin round, 0 leaves only the sieve by the primes up to 53
+    /// that every candidate goes through, so bounds up to 15 change nothing.
+    ///
+    /// The default of 2048, all odd primes up to 17881, is close to the best
+    /// for the common key sizes. A lower bound leaves more composites to the
+    /// Miller-Rabin rounds, a much higher one spends more on divisions than
+    /// the rounds they save. Smaller keys do best with a somewhat lower
+    /// bound. The bound only changes how fast a prime is found, never which
+    /// one.
+    pub trial_division_bound: usize,
+    /// Most prime candidates drawn, over all primes of the key and every
+    /// restart, before key generation gives up with `Error::KeyGenFailed`.
+    /// `None`, the default, never gives up.
+    ///
+    /// How many candidates a key takes varies widely from one rng to the
+    /// next, so a budget bounds the time spent rather than tuning it.
+    pub max_candidates: Option<usize>,
+}
+
+impl Default for KeyGenParams {
+    fn default() -> Self {
+        KeyGenParams {
+            trial_division_bound: 2048,
+            max_candidates: None,
+        }
+    }
+}
+
+/// Counters of a key generation, see `generate_multi_prime_key_with_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyGenStats {
+    /// Random prime candidates drawn, each moved up to the next number
+    /// coprime to the primes up to 53 before it is tested.
+    pub candidates: usize,
+    /// Candidates that survived trial division and went on to the primality
+    /// test of `num-bigint`, 20 Miller-Rabin rounds and a Lucas test for a
+    /// prime, usually a single round for a composite.
+    pub primality_tests: usize,
+}
 
 /// Generates a multi-prime RSA keypair of the given bit size,
 /// and the given random source, as suggested in [1]. Although the public
@@ -24,6 +80,10 @@ const EXP: u64 = 65537;
 /// public key exponent value, use `algorithms::generate_multi_prime_key_with_exp`
 /// instead.
 ///
+/// The modulus always has exactly `bit_size` bits, as FIPS 186-4 requires:
+/// every prime has its top two bits set, and primes whose product falls
+/// short are drawn again.
+///
 /// Table 1 in [2] suggests maximum numbers of primes for a given size.
 ///
 /// [1] US patent 4405829 (1972, expired)
@@ -54,6 +114,33 @@ pub fn generate_multi_prime_key_with_exp<R: Rng>(
     bit_size: usize,
     exp: &BigUint,
 ) -> Result<RSAPrivateKey> {
+    generate_multi_prime_key_with_params(rng, nprimes, bit_size, exp, &KeyGenParams::default())
+}
+
+/// Same as `generate_multi_prime_key_with_exp`, with the tunables of
+/// `params` instead of their defaults.
+pub fn generate_multi_prime_key_with_params<R: Rng>(
+    rng: &mut R,
+    nprimes: usize,
+    bit_size: usize,
+    exp: &BigUint,
+    params: &KeyGenParams,
+) -> Result<RSAPrivateKey> {
+    generate_multi_prime_key_with_stats(rng, nprimes, bit_size, exp, params).map(|(key, _)| key)
+}
+
+/// Same as `generate_multi_prime_key_with_params`, also returning how much
+/// work the key took.
+///
+/// Returns `Error::KeyGenFailed` once `params.max_candidates` candidates were
+/// drawn without finding all primes.
+pub fn generate_multi_prime_key_with_stats<R: Rng>(
+    rng: &mut R,
+    nprimes: usize,
+    bit_size: usize,
+    exp: &BigUint,
+    params: &KeyGenParams,
+) -> Result<(RSAPrivateKey, KeyGenStats)> {
     if nprimes < 2 {
         return Err(Error::NprimesTooSmall);
     }
@@ -74,13 +161,15 @@ pub fn generate_multi_prime_key_with_exp<R: Rng>(
         }
     }
 
+    let trial_division = TrialDivision::new(params.trial_division_bound);
+    let mut stats = KeyGenStats::default();
     let mut primes = vec![BigUint::zero(); nprimes];
     let n_final: BigUint;
     let d_final: BigUint;
 
     'next: loop {
         let mut todo = bit_size;
-        // `gen_prime` should set the top two bits in each prime.
+        // `generate_prime` sets the top two bits in each prime.
         // Thus each prime has the form
         //   p_i = 2^bitlen(p_i) × 0.11... (in base 2).
         // And the product is:
@@ -96,7 +185,14 @@ pub fn generate_multi_prime_key_with_exp<R: Rng>(
         }
 
         for (i, prime) in primes.iter_mut().enumerate() {
-            *prime = rng.gen_prime(todo / (nprimes - i));
+            let bits = todo / (nprimes - i);
+            *prime = generate_prime(
+                rng,
+                bits,
+                &trial_division,
+                &mut stats,
+                params.max_candidates,
+            )?;
             todo -= prime.bits();
         }
 
@@ -119,7 +215,7 @@ pub fn generate_multi_prime_key_with_exp<R: Rng>(
 
         if n.bits() != bit_size {
             // This should never happen for nprimes == 2 because
-            // gen_prime should set the top two bits in each prime.
+            // generate_prime sets the top two bits in each prime.
             // For nprimes > 2 we hope it does not happen often.
             continue 'next;
         }
@@ -131,12 +227,366 @@ pub fn generate_multi_prime_key_with_exp<R: Rng>(
         }
     }
 
-    Ok(RSAPrivateKey::from_components(
-        n_final,
-        exp.clone(),
-        d_final,
-        primes,
-    ))
+    let key = RSAPrivateKey::from_components(n_final, exp.clone(), d_final, primes);
+    Ok((key, stats))
+}
+
+/// Returns the first `count` odd primes.
+fn small_odd_primes(count: usize) -> Vec<u64> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    // The n-th prime is below n * (ln n + ln ln n) for n >= 6.
+    let n = core::cmp::max(count + 1, 6) as f64;
+    let limit = (n * (n.ln() + n.ln().ln())) as usize + 1;
+    let mut composite = vec![false; limit];
+    let mut primes = Vec::with_capacity(count);
+    for candidate in (3..limit).step_by(2) {
+        if composite[candidate] {
+            continue;
+        }
+        primes.push(candidate as u64);
+        if primes.len() == count {
+            break;
+        }
+        for multiple in (candidate * candidate..limit).step_by(2 * candidate) {
+            composite[multiple] = true;
+        }
+    }
+    primes
+}
+
+/// Trial division by small primes, grouped so that each group costs a single
+/// reduction of the candidate.
+///
+/// A single `gcd` of the candidate with the product of all primes finds the
+/// same factors, and the tests check that both reject the same candidates.
+/// The grouped reductions are kept as they are the faster of the two with
+/// `num-bigint`: each one is a division by a single limb, and most rejected
+/// candidates are caught by one of the first groups, while the `gcd` always
+/// works through the whole product.
+struct TrialDivision {
+    /// The product of each group, which fits in a `u64`, and its primes.
+    groups: Vec<(u64, Vec<u64>)>,
+}
+
+impl TrialDivision {
+    /// Divides by those of the first `count` odd primes that the sieve of
+    /// `generate_prime` does not cover already.
+    fn new(count: usize) -> Self {
+        let mut groups: Vec<(u64, Vec<u64>)> = Vec::new();
+        let primes = small_odd_primes(count);
+        for prime in primes.into_iter().skip(GEN_PRIME_SIEVE.len()) {
+            match groups.last_mut() {
+                Some((product, members)) if product.checked_mul(prime).is_some() => {
+                    *product *= prime;
+                    members.push(prime);
+                }
+                _ => groups.push((prime, vec![prime])),
+            }
+        }
+        TrialDivision { groups }
+    }
+
+    /// Returns whether one of the primes divides `candidate`, without
+    /// counting the primes themselves.
+    fn rejects(&self, candidate: &BigUint) -> bool {
+        let small = candidate.to_u64();
+        self.groups.iter().any(|(product, members)| {
+            let rem = (candidate % *product)
+                .to_u64()
+                .expect("reduced modulo a u64");
+            members
+                .iter()
+                .any(|&prime| (rem % prime).is_zero() && small != Some(prime))
+        })
+    }
+}
+
+/// The odd primes up to 53, whose product still fits in a `u64`.
+const GEN_PRIME_SIEVE: [u64; 15] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+
+/// Generates a random prime of exactly `bits` bits with the top two bits
+/// set, so that the product of two such primes has twice as many bits.
+///
+/// This is `RandPrime::gen_prime` of `num-bigint` with extra trial division
+/// before the primality test: a random odd number is moved up to the next
+/// number coprime to the primes up to 53 and tested, if it is not prime a new
+/// one is drawn. Candidates `trial_division` rejects would fail the test
+/// anyway, so for the same `rng` the same prime comes out as from
+/// `gen_prime`, only faster.
+///
+/// `rng` is the only source of randomness: the Miller-Rabin witnesses of
+/// `probably_prime` come from a generator seeded with the candidate itself,
+/// so a seeded `rng` always leads to the same prime.
+///
+/// Every candidate drawn is counted in `stats`, once `stats.candidates`
+/// reaches `max_candidates` this returns `Error::KeyGenFailed` instead of
+/// drawing another one.
+///
+/// # Panics
+///
+/// Panics if `bits < 2`.
+fn generate_prime<R: Rng>(
+    rng: &mut R,
+    bits: usize,
+    trial_division: &TrialDivision,
+    stats: &mut KeyGenStats,
+    max_candidates: Option<usize>,
+) -> Result<BigUint> {
+    assert!(bits >= 2, "prime size must be at least 2 bits");
+
+    let product = GEN_PRIME_SIEVE.iter().product::<u64>();
+    let top_bits = match bits % 8 {
+        0 => 8,
+        b => b,
+    };
+    let mut bytes = vec![0u8; (bits - top_bits) / 8 + 1];
+    let last = bytes.len() - 1;
+
+    loop {
+        if let Some(max_candidates) = max_candidates {
+            if stats.candidates >= max_candidates {
+                bytes.zeroize();
+                return Err(Error::KeyGenFailed);
+            }
+        }
+        stats.candidates += 1;
+
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= ((1u32 << top_bits) - 1) as u8;
+        if top_bits >= 2 {
+            bytes[0] |= 3u8 << (top_bits - 2);
+        } else {
+            bytes[0] |= 1;
+            bytes[1] |= 0x80;
+        }
+        bytes[last] |= 1;
+
+        let mut candidate = BigUint::from_bytes_be(&bytes);
+        let rem = (&candidate % product)
+            .to_u64()
+            .expect("reduced modulo a u64");
+        let coprime = |m: u64| {
+            GEN_PRIME_SIEVE
+                .iter()
+                .all(|&prime| !(m % prime).is_zero() || (bits <= 6 && m == prime))
+        };
+        if let Some(delta) = (0..MAX_PRIME_SEARCH_DELTA)
+            .step_by(2)
+            .find(|delta| coprime(rem + delta))
+        {
+            candidate += delta;
+        }
+
+        if candidate.bits() != bits || trial_division.rejects(&candidate) {
+            continue;
+        }
+        stats.primality_tests += 1;
+        if probably_prime(&candidate, PRIME_CANDIDATE_ROUNDS) {
+            bytes.zeroize();
+            return Ok(candidate);
+        }
+    }
+}
+
+/// Computes the private exponent `d` for the primes `p` and `q` and the
+/// public exponent `e`.
+///
+/// `d` is the inverse of `e` modulo λ(n) = lcm(p - 1, q - 1), the smallest
+/// exponent that works, which is also what OpenSSL generates. Returns
+/// `Error::InvalidKey` if the inverse does not exist.
+pub fn compute_private_exponent(p: &BigUint, q: &BigUint, e: &BigUint) -> Result<BigUint> {
+    let one = BigUint::one();
+    if p <= &one || q <= &one {
+        return Err(Error::InvalidKey);
+    }
+
+    let lambda = carmichael_totient(&[p.clone(), q.clone()]);
+    e.mod_inverse(&lambda)
+        .and_then(|d| d.to_biguint())
+        .ok_or(Error::InvalidKey)
+}
+
+/// Computes the Carmichael totient λ(n) of the product `n` of the distinct
+/// `primes`, i.e. lcm(p - 1) over all of them.
+///
+/// λ(n) divides Euler's φ(n) and is the modulus a minimal private exponent is
+/// computed with. Returns one for an empty slice.
+///
+/// # Panics
+///
+/// Panics if one of the primes is zero.
+pub fn carmichael_totient(primes: &[BigUint]) -> BigUint {
+    let one = BigUint::one();
+    primes
+        .iter()
+        .fold(one.clone(), |lambda, prime| lambda.lcm(&(prime - &one)))
+}
+
+/// Computes a square root of `a` modulo the odd prime `p` with the
+/// Tonelli-Shanks algorithm, or returns `None` if `a` is not a quadratic
+/// residue.
+///
+/// The other root is `p` minus the one returned. For `p ≡ 3 mod 4`, which
+/// is half of all primes, the root is simply `a^((p + 1) / 4)`. This runs in
+/// variable time and is meant for experiments, not for secret values.
+///
+/// `p` is not tested for primality. `None` is also returned for `p < 3`, an
+/// even `p`, and whenever a composite `p` is detected, so every root that is
+/// returned is a square root of `a`.
+pub fn sqrt_mod_prime(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let one = BigUint::one();
+    if p.bits() < 2 || p.is_even() {
+        return None;
+    }
+    let p_minus_one = p - &one;
+    let a = a % p;
+    if a.is_zero() {
+        return Some(a);
+    }
+    // Euler's criterion.
+    if a.modpow(&(&p_minus_one >> 1), p) != one {
+        return None;
+    }
+    let root = if p.get_limb(0) & 3 == 3 {
+        a.modpow(&((p + &one) >> 2), p)
+    } else {
+        tonelli_shanks(&a, p)?
+    };
+    if &root * &root % p == a {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// The general case of `sqrt_mod_prime`, for a residue `a` modulo
+/// `p ≡ 1 mod 4`. Returns `None` where it finds that `p` is composite.
+fn tonelli_shanks(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let one = BigUint::one();
+    let p_minus_one = p - &one;
+
+    // p - 1 = q * 2^s with q odd, and z any non-residue. For a prime the
+    // least non-residue is below 2 ln(p)^2 assuming the generalized Riemann
+    // hypothesis, and in practice far smaller. Some composites have none.
+    let s = p_minus_one
+        .trailing_zeros()
+        .expect("p is odd and larger than one");
+    let q = &p_minus_one >> s;
+    let limit = 2 * p.bits() * p.bits();
+    let z = (2u32..)
+        .take(limit)
+        .map(BigUint::from)
+        .take_while(|z| z < p)
+        .find(|z| z.modpow(&(&p_minus_one >> 1), p) == p_minus_one)?;
+
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = a.modpow(&q, p);
+    let mut r = a.modpow(&((&q + &one) >> 1), p);
+    while t != one {
+        // The least i with t^(2^i) = 1, which is below m for a prime.
+        let mut i = 0;
+        let mut t2i = t.clone();
+        while t2i != one {
+            if i + 1 >= m {
+                return None;
+            }
+            t2i = &t2i * &t2i % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * b % p;
+    }
+    Some(r)
+}
+
+/// Returns the number of Miller-Rabin rounds used to test the primes of a
+/// modulus of `bit_size` bits.
+///
+/// The counts follow FIPS 186-4 Table C.2 for RSA primes:
+///
+/// | modulus size        | rounds |
+/// |---------------------|--------|
+/// | below 1024 bits     | 20     |
+/// | 1024 to 3071 bits   | 5      |
+/// | 3072 bits and above | 4      |
+///
+/// FIPS 186-4 does not cover moduli below 1024 bits, they get the more
+/// conservative count `num-bigint` uses when generating primes.
+pub fn mr_rounds_for_bits(bit_size: usize) -> usize {
+    if bit_size >= 3072 {
+        4
+    } else if bit_size >= 1024 {
+        5
+    } else {
+        20
+    }
+}
+
+/// Reports whether `candidate` is probably a prime factor of a modulus of
+/// `bit_size` bits.
+///
+/// Runs `mr_rounds_for_bits(bit_size)` Miller-Rabin rounds followed by a
+/// Lucas test, the check `RSAPrivateKey::validate_full` applies. Key
+/// generation runs a stricter test of its own on every prime.
+pub fn is_probably_prime(candidate: &BigUint, bit_size: usize) -> bool {
+    probably_prime(candidate, mr_rounds_for_bits(bit_size))
+}
+
+/// Returns a rough estimate of how long `RSAPrivateKey::new(rng, bit_size)`
+/// takes on this machine.
+///
+/// This is a heuristic meant to tell apart "instant" from "move it to a
+/// background thread", expect it to be off by a factor of a few in either
+/// direction. Key generation time is random by nature, a single run may well
+/// take several times the estimate.
+///
+/// The estimate times a Miller-Rabin round on a sample candidate of the size
+/// of a prime factor and multiplies it by the expected number of rounds: by
+/// the prime number theorem about `ln(2^(bits/2)) / 2` odd candidates are
+/// needed per prime, of which the default trial division of
+/// `KeyGenParams` leaves roughly 11% for a single round, and the prime that
+/// is eventually found gets a few dozen more.
+#[cfg(feature = "std")]
+pub fn estimate_keygen_cost(bit_size: usize) -> std::time::Duration {
+    use num_bigint::RandBigInt;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::{Duration, Instant};
+
+    /// Share of odd candidates that survive trial division by the first
+    /// 2048 odd primes.
+    const SIEVE_SURVIVAL: f64 = 0.115;
+    /// Rounds `generate_prime` spends on a prime: 20 Miller-Rabin rounds and
+    /// a Lucas test, counted as four rounds.
+    const ROUNDS_PER_PRIME: usize = PRIME_CANDIDATE_ROUNDS + 4;
+
+    let prime_bits = core::cmp::max(bit_size / 2, 8);
+    let mut rng = StdRng::seed_from_u64(prime_bits as u64);
+    let candidate =
+        rng.gen_biguint(prime_bits) | BigUint::one() | (BigUint::one() << (prime_bits - 1));
+    let exp = &candidate - BigUint::one();
+    let base = BigUint::from_u64(2).unwrap();
+
+    // The fastest of a few runs, to keep scheduling noise out.
+    let round = (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            let _ = base.modpow(&exp, &candidate);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default();
+
+    let candidates = prime_bits as f64 * core::f64::consts::LN_2 / 2.0 * SIEVE_SURVIVAL;
+    let rounds = candidates + ROUNDS_PER_PRIME as f64;
+    let nanos = 2.0 * rounds * round.as_nanos() as f64;
+    Duration::from_nanos(nanos as u64)
 }
 
 /// Mask generation function.
@@ -149,9 +599,10 @@ pub fn mgf1_xor(out: &mut [u8], digest: &mut dyn DynDigest, seed: &[u8]) {
     const MAX_LEN: u64 = core::u32::MAX as u64 + 1;
     assert!(out.len() as u64 <= MAX_LEN);
 
+    let mut digest_input = vec![0u8; seed.len() + 4];
+    digest_input[0..seed.len()].copy_from_slice(seed);
+
     while i < out.len() {
-        let mut digest_input = vec![0u8; seed.len() + 4];
-        digest_input[0..seed.len()].copy_from_slice(seed);
         digest_input[seed.len()..].copy_from_slice(&counter);
 
         digest.update(digest_input.as_slice());
@@ -166,16 +617,439 @@ pub fn mgf1_xor(out: &mut [u8], digest: &mut dyn DynDigest, seed: &[u8]) {
             j += 1;
             i += 1;
         }
-        inc_counter(&mut counter);
+        if i < out.len() {
+            // The length check above keeps the number of blocks at or below
+            // 2^32, so the counter of the last block is at most 2^32 - 1.
+            inc_counter(&mut counter).expect("MGF1 counter overflow");
+        }
     }
 }
 
-fn inc_counter(counter: &mut [u8; 4]) {
-    for i in (0..4).rev() {
-        counter[i] = counter[i].wrapping_add(1);
-        if counter[i] != 0 {
-            // No overflow
-            return;
+/// Increments the big-endian counter `counter`, of any width, by one.
+///
+/// Fails with `Error::CounterOverflow` and leaves `counter` unchanged if
+/// every byte is already 0xff, instead of wrapping around to zero. An
+/// empty counter cannot be incremented either.
+pub fn inc_counter(counter: &mut [u8]) -> Result<()> {
+    let carry = match counter.iter().rposition(|&b| b != 0xff) {
+        Some(pos) => pos,
+        None => return Err(Error::CounterOverflow),
+    };
+    counter[carry] += 1;
+    for byte in counter[carry + 1..].iter_mut() {
+        *byte = 0;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::PublicKeyParts;
+
+    use digest::Digest;
+    use num_traits::ToPrimitive;
+    use num_bigint::{RandBigInt, RandPrime};
+    use rand::{rngs::StdRng, SeedableRng};
+    use sha1::Sha1;
+    use sha2::Sha256;
+
+    /// The original `mgf1_xor`, which allocates the hash input on every iteration.
+    fn mgf1_xor_reference(out: &mut [u8], digest: &mut dyn DynDigest, seed: &[u8]) {
+        let mut counter = [0u8; 4];
+        let mut i = 0;
+
+        while i < out.len() {
+            let mut digest_input = vec![0u8; seed.len() + 4];
+            digest_input[0..seed.len()].copy_from_slice(seed);
+            digest_input[seed.len()..].copy_from_slice(&counter);
+
+            digest.update(digest_input.as_slice());
+            let digest_output = &*digest.finalize_reset();
+            for byte in digest_output.iter().take(out.len() - i) {
+                out[i] ^= byte;
+                i += 1;
+            }
+            counter = (u32::from_be_bytes(counter) + 1).to_be_bytes();
+        }
+    }
+
+    #[test]
+    fn test_inc_counter() {
+        let cases: [(u32, u32); 7] = [
+            (0, 1),
+            (0x0000_00fe, 0x0000_00ff),
+            (0x0000_00ff, 0x0000_0100),
+            (0x0000_ffff, 0x0001_0000),
+            (0x00ff_ffff, 0x0100_0000),
+            (0x0102_03ff, 0x0102_0400),
+            (0xffff_fffe, 0xffff_ffff),
+        ];
+        for &(before, after) in cases.iter() {
+            let mut counter = before.to_be_bytes();
+            inc_counter(&mut counter).unwrap();
+            assert_eq!(u32::from_be_bytes(counter), after, "{:#010x} + 1", before);
+        }
+
+        // Every carry out of the low byte, and every lower counter value.
+        for value in 0..=0x1_ffffu32 {
+            let mut counter = value.to_be_bytes();
+            inc_counter(&mut counter).unwrap();
+            assert_eq!(u32::from_be_bytes(counter), value + 1);
         }
+
+        let mut counter = [0xff; 4];
+        match inc_counter(&mut counter) {
+            Err(Error::CounterOverflow) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(counter, [0xff; 4]);
+
+        // Other widths.
+        let mut counter = [0x00, 0xff];
+        inc_counter(&mut counter).unwrap();
+        assert_eq!(counter, [0x01, 0x00]);
+        let mut counter = [0u8; 9];
+        counter[1..].copy_from_slice(&[0xff; 8]);
+        inc_counter(&mut counter).unwrap();
+        assert_eq!(counter, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(inc_counter(&mut [0xff]).is_err());
+        assert!(inc_counter(&mut []).is_err());
+    }
+
+    #[test]
+    fn test_mr_rounds_for_bits() {
+        assert_eq!(mr_rounds_for_bits(1024), 5);
+        assert_eq!(mr_rounds_for_bits(2048), 5);
+        assert_eq!(mr_rounds_for_bits(3072), 4);
+        assert_eq!(mr_rounds_for_bits(4096), 4);
+        assert_eq!(mr_rounds_for_bits(3071), 5);
+        assert_eq!(mr_rounds_for_bits(512), 20);
+    }
+
+    #[test]
+    fn test_estimate_keygen_cost() {
+        let small = estimate_keygen_cost(1024);
+        let large = estimate_keygen_cost(4096);
+        assert!(small > std::time::Duration::from_nanos(0));
+        // A round on primes twice as large costs several times as much.
+        assert!(large > small * 2, "{:?} vs {:?}", large, small);
+        assert!(estimate_keygen_cost(0) > std::time::Duration::from_nanos(0));
+    }
+
+    #[test]
+    fn test_is_probably_prime() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let key = generate_multi_prime_key(&mut rng, 2, 1024).unwrap();
+        for prime in key.primes() {
+            assert!(is_probably_prime(prime, 1024));
+        }
+        let product = &key.primes()[0] * &key.primes()[1];
+        assert!(!is_probably_prime(&product, 2048));
+        assert!(!is_probably_prime(&(&key.primes()[0] + 2u32), 1024));
+    }
+
+    #[test]
+    fn test_small_odd_primes() {
+        assert!(small_odd_primes(0).is_empty());
+        assert_eq!(small_odd_primes(8), [3, 5, 7, 11, 13, 17, 19, 23]);
+        let primes = small_odd_primes(2048);
+        assert_eq!(primes.len(), 2048);
+        assert_eq!(primes.last(), Some(&17881));
+    }
+
+    #[test]
+    fn test_generate_prime() {
+        let trial_division = TrialDivision::new(KeyGenParams::default().trial_division_bound);
+        let generate = |bits, trial_division: &TrialDivision| {
+            let mut stats = KeyGenStats::default();
+            let mut rng = StdRng::seed_from_u64(0);
+            generate_prime(&mut rng, bits, trial_division, &mut stats, None).unwrap()
+        };
+        for &bits in [2, 3, 6, 7, 8, 9, 16, 64, 512].iter() {
+            let prime = generate(bits, &trial_division);
+            assert_eq!(prime.bits(), bits);
+            assert!(prime >= BigUint::from(3u32) << (bits - 2));
+            assert!(probably_prime(&prime, 20));
+
+            // Trial division only skips composites, so the same prime as
+            // from `gen_prime` is found with any bound.
+            assert_eq!(prime, StdRng::seed_from_u64(0).gen_prime(bits));
+            let other = generate(bits, &TrialDivision::new(0));
+            assert_eq!(other, prime);
+        }
+    }
+
+    #[test]
+    fn test_trial_division() {
+        let trial_division = TrialDivision::new(2048);
+        assert!(TrialDivision::new(15).groups.is_empty());
+        let members: Vec<u64> = trial_division
+            .groups
+            .iter()
+            .flat_map(|(_, members)| members.iter().cloned())
+            .collect();
+        assert_eq!(members.len(), 2048 - 15);
+        assert_eq!(members[0], 59);
+
+        assert!(trial_division.rejects(&BigUint::from(59u32 * 61)));
+        assert!(trial_division.rejects(&(BigUint::from(17881u32) << 100)));
+        assert!(!trial_division.rejects(&BigUint::from(17881u32)));
+        assert!(!trial_division.rejects(&BigUint::from(53u32 * 53)));
+    }
+
+    #[test]
+    fn test_trial_division_matches_individual_division() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let primes = small_odd_primes(2048);
+        let screened = &primes[GEN_PRIME_SIEVE.len()..];
+        let product = screened.iter().fold(BigUint::one(), |acc, &p| acc * p);
+        let trial_division = TrialDivision::new(2048);
+
+        // Every tenth prime itself and squared, and random odd numbers.
+        let some_primes = screened.iter().step_by(10).map(|&p| BigUint::from(p));
+        let mut candidates: Vec<BigUint> = some_primes.clone().collect();
+        candidates.extend(some_primes.map(|p| &p * &p));
+        for &bits in [16, 64, 512, 1024].iter() {
+            candidates.extend((0..50).map(|_| rng.gen_biguint(bits) | BigUint::one()));
+        }
+
+        let mut rejected = 0;
+        for candidate in &candidates {
+            let individually = screened
+                .iter()
+                .any(|&p| (candidate % p).is_zero() && *candidate != BigUint::from(p));
+            assert_eq!(
+                trial_division.rejects(candidate),
+                individually,
+                "{}",
+                candidate
+            );
+
+            // For candidates above the largest prime this is the same as a
+            // common factor with the product of all of them.
+            if candidate.bits() > 16 {
+                assert_eq!(individually, !candidate.gcd(&product).is_one());
+            }
+            rejected += individually as usize;
+        }
+        assert!(rejected > candidates.len() / 2);
+    }
+
+    #[test]
+    fn test_generate_with_params() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let exp = BigUint::from_u64(EXP).unwrap();
+        for &trial_division_bound in [0, 1, 100].iter() {
+            let params = KeyGenParams {
+                trial_division_bound,
+                ..KeyGenParams::default()
+            };
+            for &nprimes in [2, 3].iter() {
+                let key =
+                    generate_multi_prime_key_with_params(&mut rng, nprimes, 512, &exp, &params)
+                        .unwrap();
+                assert_eq!(key.n().bits(), 512);
+                assert_eq!(key.primes().len(), nprimes);
+                key.validate().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_exact_bit_length() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for &(nprimes, bit_size) in [(2, 64), (2, 127), (2, 255), (3, 257), (4, 384)].iter() {
+            for _ in 0..25 {
+                let key = generate_multi_prime_key(&mut rng, nprimes, bit_size).unwrap();
+                assert_eq!(key.n().bits(), bit_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sqrt_mod_prime() {
+        // p - 1 divisible by 2, 4, 8, 32 and 65536, and a p ≡ 3 mod 4.
+        for &p in &[3u64, 5, 17, 41, 97, 65537, 1_000_003] {
+            let modulus = BigUint::from_u64(p).unwrap();
+            let mut residues = 0;
+            for a in (0..p).step_by(core::cmp::max(1, p as usize / 1000)) {
+                let a = BigUint::from_u64(a).unwrap();
+                let euler = a.modpow(&((&modulus - 1u32) >> 1), &modulus);
+                let is_square = a.is_zero() || euler.is_one();
+                match sqrt_mod_prime(&a, &modulus) {
+                    Some(root) => {
+                        assert!(is_square, "{} mod {}", a, p);
+                        assert!(root < modulus);
+                        assert_eq!(&root * &root % &modulus, a);
+                        residues += 1;
+                    }
+                    None => assert!(!is_square, "{} mod {}", a, p),
+                }
+            }
+            assert!(residues > 1);
+        }
+
+        // Values above p are reduced first.
+        let p = BigUint::from_u64(13).unwrap();
+        let root = sqrt_mod_prime(&BigUint::from_u64(13 + 10).unwrap(), &p).unwrap();
+        assert_eq!(&root * &root % &p, BigUint::from_u64(10).unwrap());
+
+        // Moduli that are not odd primes give None or a real root, and never
+        // hang or panic. 9, 21, 25, 561 and 1105 are ≡ 1 mod 4.
+        for &m in &[0u64, 1, 2, 4, 9, 15, 21, 25, 561, 1105, 1 << 20] {
+            let modulus = BigUint::from_u64(m).unwrap();
+            for a in 0..core::cmp::min(m, 200) {
+                let a = BigUint::from_u64(a).unwrap();
+                if let Some(root) = sqrt_mod_prime(&a, &modulus) {
+                    assert_eq!(&root * &root % &modulus, a, "{} mod {}", a, m);
+                }
+            }
+            if m < 3 || m & 1 == 0 {
+                assert_eq!(sqrt_mod_prime(&BigUint::one(), &modulus), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_with_stats() {
+        let exp = BigUint::from_u64(EXP).unwrap();
+        let params = KeyGenParams::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let (key, stats) =
+            generate_multi_prime_key_with_stats(&mut rng, 2, 512, &exp, &params).unwrap();
+        // The stats do not change which key comes out.
+        let mut rng = StdRng::seed_from_u64(0);
+        let same = generate_multi_prime_key_with_params(&mut rng, 2, 512, &exp, &params).unwrap();
+        assert_eq!(key, same);
+        assert!(stats.primality_tests >= 2);
+        assert!(stats.candidates >= stats.primality_tests);
+
+        // With the exact budget the same key is found, one less is not enough.
+        let params = KeyGenParams {
+            max_candidates: Some(stats.candidates),
+            ..KeyGenParams::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let result = generate_multi_prime_key_with_stats(&mut rng, 2, 512, &exp, &params);
+        assert_eq!(result.unwrap(), (key, stats));
+        for &max_candidates in [0, 1, stats.candidates - 1].iter() {
+            let params = KeyGenParams {
+                max_candidates: Some(max_candidates),
+                ..KeyGenParams::default()
+            };
+            let mut rng = StdRng::seed_from_u64(0);
+            match generate_multi_prime_key_with_params(&mut rng, 2, 512, &exp, &params) {
+                Err(Error::KeyGenFailed) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_mgf1_xor() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for len in &[0, 1, 19, 20, 21, 31, 32, 33, 64, 255, 256, 4096 + 7] {
+            let mut seed = vec![0u8; rng.gen_range(0, 64)];
+            rng.fill(&mut seed[..]);
+            let mut input = vec![0u8; *len];
+            rng.fill(&mut input[..]);
+
+            let mut digests: [Box<dyn DynDigest>; 2] =
+                [Box::new(Sha1::new()), Box::new(Sha256::new())];
+            for digest in digests.iter_mut() {
+                let mut out = input.clone();
+                let mut expected = input.clone();
+                mgf1_xor(&mut out, &mut **digest, &seed);
+                mgf1_xor_reference(&mut expected, &mut **digest, &seed);
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_private_exponent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let one = BigUint::one();
+
+        for _ in 0..10 {
+            let key = RSAPrivateKey::new(&mut rng, 512).expect("failed to generate key");
+            let (p, q) = (&key.primes()[0], &key.primes()[1]);
+            let lambda = (p - &one).lcm(&(q - &one));
+
+            let d = compute_private_exponent(p, q, key.e()).expect("failed to compute d");
+            assert!(d < lambda);
+            assert_eq!((key.e() * &d) % &lambda, one);
+
+            let rebuilt = RSAPrivateKey::from_components(
+                key.n().clone(),
+                key.e().clone(),
+                d,
+                vec![p.clone(), q.clone()],
+            );
+            rebuilt.validate().expect("invalid rebuilt key");
+        }
+    }
+
+    #[test]
+    fn test_carmichael_totient() {
+        let lambda = |primes: &[u64]| {
+            let primes: Vec<BigUint> = primes
+                .iter()
+                .map(|&p| BigUint::from_u64(p).unwrap())
+                .collect();
+            carmichael_totient(&primes).to_u64().unwrap()
+        };
+
+        assert_eq!(lambda(&[]), 1);
+        assert_eq!(lambda(&[3, 5]), 4);
+        assert_eq!(lambda(&[3, 7]), 6);
+        assert_eq!(lambda(&[5, 7]), 12);
+        assert_eq!(lambda(&[7, 11]), 30);
+        assert_eq!(lambda(&[11, 13]), 60);
+        assert_eq!(lambda(&[3, 5, 7]), 12);
+        assert_eq!(lambda(&[61, 53]), 780);
+
+        // λ(n) is the smallest m with a^m = 1 mod n for every unit a.
+        let small_primes = [2u64, 3, 5, 7, 11, 13, 17, 19, 23];
+        for (i, &p) in small_primes.iter().enumerate() {
+            for &q in &small_primes[i + 1..] {
+                let n = p * q;
+                let order = |a: u64| {
+                    let mut m = 1;
+                    let mut power = a;
+                    while power != 1 {
+                        power = power * a % n;
+                        m += 1;
+                    }
+                    m
+                };
+                let expected = (1..n)
+                    .filter(|a| a.gcd(&n) == 1)
+                    .map(order)
+                    .fold(1, |acc, m| acc.lcm(&m));
+                assert_eq!(lambda(&[p, q]), expected, "n = {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_private_exponent_no_inverse() {
+        let p = BigUint::from_u64(7).unwrap();
+        let q = BigUint::from_u64(11).unwrap();
+
+        // lcm(6, 10) = 30 shares a factor with 3.
+        let e = BigUint::from_u64(3).unwrap();
+        assert!(compute_private_exponent(&p, &q, &e).is_err());
+
+        let e = BigUint::from_u64(7).unwrap();
+        assert_eq!(
+            compute_private_exponent(&p, &q, &e).unwrap(),
+            BigUint::from_u64(13).unwrap()
+        );
+
+        assert!(compute_private_exponent(&BigUint::one(), &q, &e).is_err());
+        assert!(compute_private_exponent(&p, &BigUint::zero(), &e).is_err());
     }
 }
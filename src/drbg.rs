@@ -0,0 +1,298 @@
+//! PSS signing from a provisioned seed, for devices without an entropy
+//! source at signing time.
+//!
+//! [`CtrDrbg`] is the CTR_DRBG of NIST SP 800-90A with AES-256 and without a
+//! derivation function. [`SeededSigner`] draws both the PSS salts and the
+//! blinding factors from it, so the signatures are as unpredictable as with
+//! a live rng, while the same seed reproduces the same signatures.
+//!
+//! # Security model
+//!
+//! The output is only as good as the seed. It must be 48 bytes of full
+//! entropy from a proper source, generated when the device is provisioned,
+//! kept as secret as the private key and never given to a second signer: two
+//! signers started from the same seed produce the same salts and blinding
+//! factors. Restarting from the same seed after a reboot replays earlier
+//! salts, so reseed with [`CtrDrbg::reseed`] whenever fresh entropy becomes
+//! available. If no secret seed can be provisioned at all,
+//! `pss::sign_deterministic_salt` derives the salt from the private key
+//! instead, which is still better than an empty salt.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use aes::cipher::block::Block;
+use aes::{Aes256, BlockCipher, NewBlockCipher};
+use digest::{Digest, DynDigest};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::errors::Result;
+use crate::key::RSAPrivateKey;
+use crate::pss;
+
+/// Length of the AES-256 key, `keylen` in SP 800-90A.
+const KEY_LEN: usize = 32;
+
+/// Length of an AES block, `blocklen` in SP 800-90A.
+const BLOCK_LEN: usize = 16;
+
+/// Length of a seed, `seedlen` in SP 800-90A.
+pub const SEED_LEN: usize = KEY_LEN + BLOCK_LEN;
+
+/// Most bytes a single generate call may return, 2^19 bits.
+const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// CTR_DRBG with AES-256 and without a derivation function, NIST SP 800-90A
+/// section 10.2.1.
+///
+/// `fill_bytes` splits long requests into generate calls of at most
+/// 2^16 bytes each. The reseed interval of 2^48 requests is not enforced, no
+/// signer gets anywhere near it.
+pub struct CtrDrbg {
+    key: [u8; KEY_LEN],
+    v: [u8; BLOCK_LEN],
+}
+
+impl CtrDrbg {
+    /// Instantiates the DRBG with `seed` as the entropy input and no
+    /// personalization string.
+    pub fn new(seed: &[u8; SEED_LEN]) -> Self {
+        let mut drbg = CtrDrbg {
+            key: [0; KEY_LEN],
+            v: [0; BLOCK_LEN],
+        };
+        drbg.update(Some(seed));
+        drbg
+    }
+
+    /// Mixes fresh entropy into the state, SP 800-90A section 10.2.1.4.
+    pub fn reseed(&mut self, entropy: &[u8; SEED_LEN]) {
+        self.update(Some(entropy));
+    }
+
+    /// The update function of SP 800-90A section 10.2.1.2.
+    fn update(&mut self, provided_data: Option<&[u8; SEED_LEN]>) {
+        let mut temp = [0u8; SEED_LEN];
+        self.encrypt_counter(&mut temp);
+        if let Some(data) = provided_data {
+            temp.iter_mut().zip(data.iter()).for_each(|(t, d)| *t ^= d);
+        }
+        self.key.copy_from_slice(&temp[..KEY_LEN]);
+        self.v.copy_from_slice(&temp[KEY_LEN..]);
+        temp.zeroize();
+    }
+
+    /// The generate function of SP 800-90A section 10.2.1.5.1, without
+    /// additional input.
+    fn generate(&mut self, out: &mut [u8]) {
+        debug_assert!(out.len() <= MAX_REQUEST_LEN);
+        self.encrypt_counter(out);
+        self.update(None);
+    }
+
+    /// Fills `out` with the encryptions of `V + 1`, `V + 2`, ..., leaving `V`
+    /// at the last counter used.
+    fn encrypt_counter(&mut self, out: &mut [u8]) {
+        let cipher = Aes256::new_varkey(&self.key).expect("AES-256 takes 32 byte keys");
+        for chunk in out.chunks_mut(BLOCK_LEN) {
+            increment(&mut self.v);
+            let mut block = Block::<Aes256>::default();
+            block.copy_from_slice(&self.v);
+            cipher.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            block.zeroize();
+        }
+    }
+}
+
+/// Adds one to the big-endian counter `v`, modulo `2^128`.
+fn increment(v: &mut [u8; BLOCK_LEN]) {
+    for byte in v.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+impl RngCore for CtrDrbg {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(MAX_REQUEST_LEN) {
+            self.generate(chunk);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> core::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for CtrDrbg {}
+
+impl fmt::Debug for CtrDrbg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CtrDrbg { .. }")
+    }
+}
+
+impl Drop for CtrDrbg {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.v.zeroize();
+    }
+}
+
+/// Signs with RSASSA-PSS, drawing salts and blinding factors from a
+/// [`CtrDrbg`] seeded once, see the [module documentation](self) for when
+/// this is safe to use.
+#[derive(Debug)]
+pub struct SeededSigner {
+    key: RSAPrivateKey,
+    drbg: CtrDrbg,
+}
+
+impl SeededSigner {
+    /// Wraps `key`, with the DRBG instantiated from `seed`.
+    pub fn new(key: RSAPrivateKey, seed: &[u8; SEED_LEN]) -> Self {
+        SeededSigner {
+            key,
+            drbg: CtrDrbg::new(seed),
+        }
+    }
+
+    /// Returns the wrapped key.
+    pub fn key(&self) -> &RSAPrivateKey {
+        &self.key
+    }
+
+    /// Mixes fresh entropy into the DRBG, see [`CtrDrbg::reseed`].
+    pub fn reseed(&mut self, entropy: &[u8; SEED_LEN]) {
+        self.drbg.reseed(entropy)
+    }
+
+    /// Calculates an RSASSA-PSS signature of `hashed`, using `H` as the hash
+    /// function and a salt as long as its output, which verifies with
+    /// `pss::verify` as usual.
+    pub fn sign_pss<H: Digest + DynDigest>(&mut self, hashed: &[u8]) -> Result<Vec<u8>> {
+        let salt_len = <H as Digest>::output_size();
+        pss::sign_pss_with_salt_out::<H, _, _>(&self.key, &mut self.drbg, hashed, salt_len)
+            .map(|(sig, _)| sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    use sha2::{Sha256, Sha384};
+
+    fn seed(byte: u8) -> [u8; SEED_LEN] {
+        let mut seed = [0u8; SEED_LEN];
+        for (i, b) in seed.iter_mut().enumerate() {
+            *b = byte ^ i as u8;
+        }
+        seed
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut v = [0u8; BLOCK_LEN];
+        v[BLOCK_LEN - 1] = 0xff;
+        increment(&mut v);
+        assert_eq!(&v[BLOCK_LEN - 2..], &[1, 0]);
+
+        let mut v = [0xff; BLOCK_LEN];
+        increment(&mut v);
+        assert_eq!(v, [0; BLOCK_LEN]);
+    }
+
+    #[test]
+    fn test_drbg_cavp() {
+        // NIST CAVP drbgvectors_no_reseed, CTR_DRBG.rsp, [AES-256 no df],
+        // PredictionResistance = False, no personalization string or
+        // additional input, COUNT = 0: instantiate, then generate 512 bits
+        // twice and compare the second output.
+        let entropy = hex::decode(
+            "df5d73faa468649edda33b5cca79b0b05600419ccb7a879d\
+             dfec9db32ee494e5531b51de16a30f769262474c73bec010",
+        )
+        .unwrap();
+        let expected = hex::decode(
+            "d1c07cd95af8a7f11012c84ce48bb8cb87189e99d40fccb1771c619bdf82ab22\
+             80b1dc2f2581f39164f7ac0c510494b3a43c41b7db17514c87b107ae793e01c5",
+        )
+        .unwrap();
+
+        let mut seed = [0u8; SEED_LEN];
+        seed.copy_from_slice(&entropy);
+        let mut drbg = CtrDrbg::new(&seed);
+        let mut out = [0u8; 64];
+        drbg.fill_bytes(&mut out);
+        drbg.fill_bytes(&mut out);
+        assert_eq!(&out[..], &expected[..]);
+    }
+
+    #[test]
+    fn test_drbg_output() {
+        let mut a = CtrDrbg::new(&seed(1));
+        let mut b = CtrDrbg::new(&seed(1));
+        let mut c = CtrDrbg::new(&seed(2));
+
+        let (mut out_a, mut out_b, mut out_c) = ([0u8; 100], [0u8; 100], [0u8; 100]);
+        a.fill_bytes(&mut out_a);
+        b.fill_bytes(&mut out_b);
+        c.fill_bytes(&mut out_c);
+        assert_eq!(&out_a[..], &out_b[..]);
+        assert_ne!(&out_a[..], &out_c[..]);
+
+        // Every request moves the state on, and so does a reseed.
+        a.fill_bytes(&mut out_a);
+        assert_ne!(&out_a[..], &out_b[..]);
+        b.fill_bytes(&mut out_b);
+        assert_eq!(&out_a[..], &out_b[..]);
+        b.reseed(&seed(3));
+        a.fill_bytes(&mut out_a);
+        b.fill_bytes(&mut out_b);
+        assert_ne!(&out_a[..], &out_b[..]);
+    }
+
+    #[test]
+    fn test_seeded_signer() {
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"testing");
+
+        let mut a = SeededSigner::new(priv_key.clone(), &seed(1));
+        let mut b = SeededSigner::new(priv_key.clone(), &seed(1));
+        let mut c = SeededSigner::new(priv_key.clone(), &seed(2));
+
+        let sig_a = a.sign_pss::<Sha256>(&hashed).unwrap();
+        assert_eq!(sig_a, b.sign_pss::<Sha256>(&hashed).unwrap());
+        assert_ne!(sig_a, c.sign_pss::<Sha256>(&hashed).unwrap());
+        pss::verify(&pub_key, &hashed, &sig_a, &mut Sha256::new()).expect("failed to verify");
+
+        // The next signature of the same digest gets a new salt.
+        let next = a.sign_pss::<Sha256>(&hashed).unwrap();
+        assert_ne!(next, sig_a);
+        pss::verify(&pub_key, &hashed, &next, &mut Sha256::new()).expect("failed to verify");
+
+        let hashed = Sha384::digest(b"testing");
+        let sig = a.sign_pss::<Sha384>(&hashed).unwrap();
+        pss::verify(&pub_key, &hashed, &sig, &mut Sha384::new()).expect("failed to verify");
+    }
+}
@@ -1,5 +1,7 @@
 use alloc::string::String;
 
+use crate::policy::WeakKeyReason;
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 /// Error types
@@ -22,6 +24,45 @@ pub enum Error {
     EncodeError { reason: String },
     Internal,
     LabelTooLong,
+    InvalidKey,
+    KeyTooSmall,
+    /// The signature is not as long as the modulus. Unlike `Verification`
+    /// this is reported before any cryptographic check.
+    SignatureLength {
+        expected: usize,
+        got: usize,
+    },
+    /// A signature or ciphertext passed to `validate_representative` is not
+    /// smaller than the modulus.
+    RepresentativeOutOfRange,
+    /// The digest is not as long as the output of the hash function, most
+    /// likely the message was passed instead of its hash or the hash was
+    /// truncated.
+    DigestLength {
+        expected: usize,
+        got: usize,
+    },
+    /// The public key failed a check of its `SecurityPolicy`.
+    WeakKey {
+        reason: WeakKeyReason,
+    },
+    /// Private key operations kept failing their consistency check, see
+    /// `RSAPrivateKey::enable_fault_detection`.
+    FaultDetected,
+    /// Key generation drew `KeyGenParams::max_candidates` prime candidates
+    /// without finding all primes.
+    KeyGenFailed,
+    /// `RSAPrivateKey::sqrt_mod_n` was asked for the square root of a value
+    /// that has none.
+    NotQuadraticResidue,
+    /// `algorithms::inc_counter` was asked to increment a counter that is
+    /// already at its largest value.
+    CounterOverflow,
+    /// An argument has a value the function cannot work with, `reason` says
+    /// which one.
+    InvalidArgument {
+        reason: String,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -46,6 +87,25 @@ impl core::fmt::Display for Error {
             Error::EncodeError { reason } => write!(f, "encoding error: {}", reason),
             Error::Internal => write!(f, "internal error"),
             Error::LabelTooLong => write!(f, "label too long"),
+            Error::InvalidKey => write!(f, "invalid key"),
+            Error::KeyTooSmall => write!(f, "key too small"),
+            Error::SignatureLength { expected, got } => write!(
+                f,
+                "signature length mismatch: expected {} bytes, got {}",
+                expected, got
+            ),
+            Error::RepresentativeOutOfRange => write!(f, "representative out of range"),
+            Error::DigestLength { expected, got } => write!(
+                f,
+                "digest length mismatch: expected {} bytes, got {}; the input must be hashed",
+                expected, got
+            ),
+            Error::WeakKey { reason } => write!(f, "weak key: {}", reason),
+            Error::FaultDetected => write!(f, "repeated faults in private key operations"),
+            Error::KeyGenFailed => write!(f, "too many prime candidates tried"),
+            Error::NotQuadraticResidue => write!(f, "not a quadratic residue"),
+            Error::CounterOverflow => write!(f, "counter overflow"),
+            Error::InvalidArgument { reason } => write!(f, "invalid argument: {}", reason),
         }
     }
 }
\ No newline at end of file
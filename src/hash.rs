@@ -1,5 +1,12 @@
+use alloc::boxed::Box;
+use digest::{Digest, DynDigest};
+#[cfg(feature = "sha2")]
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+
+use crate::errors::{Error, Result};
+
 /// A list of provided hashes, implementing `Hash`.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Hash {
     MD5,
     SHA1,
@@ -32,6 +39,16 @@ impl Hash {
         }
     }
 
+    /// Returns a fresh digest of this hash function as a trait object, for
+    /// picking the hash at runtime, e.g. from the `alg` of a JWT, and passing
+    /// it to `pss::verify_pss_dyn`.
+    ///
+    /// Only the SHA-2 functions are available, and only with the `sha2`
+    /// feature, the others fail with `Error::InvalidPaddingScheme`.
+    pub fn new_dyn_digest(&self) -> Result<Box<dyn DynDigest>> {
+        new_digest(*self)
+    }
+
     /// Returns the ASN1 DER prefix for the the hash function.
     pub fn asn1_prefix(&self) -> &'static [u8] {
         match *self {
@@ -83,3 +100,109 @@ impl Hash {
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl Hash {
+    /// Returns the object identifier of the hash function, or `None` for
+    /// `MD5SHA1`, which does not have one.
+    pub(crate) fn oid(&self) -> Option<simple_asn1::OID> {
+        use alloc::vec::Vec;
+        use simple_asn1::{oid, BigUint, OID};
+
+        match *self {
+            Hash::MD5 => Some(oid!(1, 2, 840, 113549, 2, 5)),
+            Hash::SHA1 => Some(oid!(1, 3, 14, 3, 2, 26)),
+            Hash::SHA2_224 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 4)),
+            Hash::SHA2_256 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 1)),
+            Hash::SHA2_384 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 2)),
+            Hash::SHA2_512 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 3)),
+            Hash::SHA3_256 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 8)),
+            Hash::SHA3_384 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 9)),
+            Hash::SHA3_512 => Some(oid!(2, 16, 840, 1, 101, 3, 4, 2, 10)),
+            Hash::MD5SHA1 => None,
+            Hash::RIPEMD160 => Some(oid!(1, 3, 36, 3, 2, 1)),
+        }
+    }
+
+    /// Looks up the hash function with the given object identifier.
+    pub(crate) fn from_oid(oid: &simple_asn1::OID) -> Option<Hash> {
+        const HASHES: [Hash; 10] = [
+            Hash::MD5,
+            Hash::SHA1,
+            Hash::SHA2_224,
+            Hash::SHA2_256,
+            Hash::SHA2_384,
+            Hash::SHA2_512,
+            Hash::SHA3_256,
+            Hash::SHA3_384,
+            Hash::SHA3_512,
+            Hash::RIPEMD160,
+        ];
+
+        HASHES
+            .iter()
+            .find(|hash| hash.oid().as_ref() == Some(oid))
+            .cloned()
+    }
+}
+
+/// Checks that `hashed` is as long as the output of `H`, as signing and
+/// verifying require, so that a message passed in place of its hash or a
+/// truncated hash is caught early.
+///
+/// Fails with `Error::DigestLength`, which states both lengths.
+pub fn check_digest_len<H: Digest>(hashed: &[u8]) -> Result<()> {
+    check_len(hashed, <H as Digest>::output_size())
+}
+
+pub(crate) fn check_len(hashed: &[u8], expected: usize) -> Result<()> {
+    if hashed.len() != expected {
+        return Err(Error::DigestLength {
+            expected,
+            got: hashed.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Returns a fresh digest for `hash`, for the SHA-2 functions, which are the
+/// only ones this crate implements itself, with the `sha2` feature.
+pub(crate) fn new_digest(hash: Hash) -> Result<Box<dyn DynDigest>> {
+    match hash {
+        #[cfg(feature = "sha2")]
+        Hash::SHA2_224 => Ok(Box::new(<Sha224 as Digest>::new())),
+        #[cfg(feature = "sha2")]
+        Hash::SHA2_256 => Ok(Box::new(<Sha256 as Digest>::new())),
+        #[cfg(feature = "sha2")]
+        Hash::SHA2_384 => Ok(Box::new(<Sha384 as Digest>::new())),
+        #[cfg(feature = "sha2")]
+        Hash::SHA2_512 => Ok(Box::new(<Sha512 as Digest>::new())),
+        _ => Err(Error::InvalidPaddingScheme),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use sha2::{Sha256, Sha512};
+
+    #[test]
+    fn test_check_digest_len() {
+        let hashed = Sha256::digest(b"testing");
+        assert!(check_digest_len::<Sha256>(&hashed).is_ok());
+
+        match check_digest_len::<Sha512>(&hashed) {
+            Err(Error::DigestLength {
+                expected: 64,
+                got: 32,
+            }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let err = check_digest_len::<Sha256>(b"testing").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "digest length mismatch: expected 32 bytes, got 7; the input must be hashed"
+        );
+    }
+}
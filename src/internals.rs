@@ -1,7 +1,10 @@
 use num_bigint::{BigInt, BigUint, IntoBigInt, IntoBigUint, ModInverse, RandBigInt, ToBigInt};
-use num_traits::{One, Signed, Zero};
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 use rand::Rng;
+use subtle::{Choice, ConstantTimeEq};
 use alloc::borrow::Cow;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use zeroize::Zeroize;
 use alloc::vec::Vec;
 use alloc::vec;
@@ -12,13 +15,159 @@ use crate::key::{PublicKeyParts, RSAPrivateKey};
 /// Raw RSA encryption of m with the public key. No padding is performed.
 #[inline]
 pub fn encrypt<K: PublicKeyParts>(key: &K, m: &BigUint) -> BigUint {
-    m.modpow(key.e(), key.n())
+    encrypt_with(&NumBigint, key, m)
+}
+
+/// Raw RSA encryption of m like [`encrypt`], exponentiating with `backend`.
+#[inline]
+pub fn encrypt_with<M: ModPow, K: PublicKeyParts>(backend: &M, key: &K, m: &BigUint) -> BigUint {
+    backend.modpow_public(m, key.e(), key.n())
+}
+
+/// Modular exponentiation, the one big-integer operation the raw primitives
+/// are built on.
+///
+/// [`encrypt_with`] and [`decrypt_and_check_with`] do all their
+/// exponentiations through this trait, so that tests and fuzz targets can
+/// swap in an instrumented or reference implementation and compare.
+pub trait ModPow {
+    /// Computes `base^exp mod modulus` for a secret `exp`.
+    fn modpow(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint;
+
+    /// Computes `base^exp mod modulus` for a public `exp`, which may branch
+    /// on its bits. Short exponents use square-and-multiply by default.
+    fn modpow_public(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+        match exp.to_u64() {
+            Some(exp) => pow_mod_small(base, exp, modulus),
+            None => self.modpow(base, exp, modulus),
+        }
+    }
+}
+
+/// The [`ModPow`] used by default, `BigUint::modpow` of `num-bigint`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumBigint;
+
+impl ModPow for NumBigint {
+    fn modpow(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+        base.modpow(exp, modulus)
+    }
+}
+
+/// Computes `base^exp mod modulus` for an exponent that fits in a `u64`.
+///
+/// Public exponents are short and sparse, 65537 is 17 bits with only two set,
+/// so plain left-to-right square-and-multiply needs 16 squarings and a single
+/// multiplication. That is cheaper than the Montgomery setup and window table
+/// `BigUint::modpow` pays for before the first squaring. The exponent is public,
+/// so branching on its bits is fine.
+fn pow_mod_small(base: &BigUint, exp: u64, modulus: &BigUint) -> BigUint {
+    if modulus.is_one() {
+        return BigUint::zero();
+    }
+    if exp == 0 {
+        return BigUint::one();
+    }
+
+    let base = base % modulus;
+    let mut acc = base.clone();
+    for i in (0..63 - exp.leading_zeros()).rev() {
+        acc = &acc * &acc % modulus;
+        if exp >> i & 1 == 1 {
+            acc = acc * &base % modulus;
+        }
+    }
+    acc
+}
+
+/// Barrett reduction modulo a fixed `n` of `k` bits, with the precomputed
+/// `mu = floor(2^(2k) / n)`.
+///
+/// A reduction costs two multiplications and a shift instead of a long
+/// division, which pays off when the same modulus is used over and over, as
+/// it is by a server verifying many signatures with one key.
+#[derive(Debug, Clone)]
+pub(crate) struct Barrett {
+    n: BigUint,
+    mu: BigUint,
+    k: usize,
+}
+
+impl Barrett {
+    pub(crate) fn new(n: &BigUint) -> Self {
+        let k = n.bits();
+        Barrett {
+            n: n.clone(),
+            mu: (BigUint::one() << (2 * k)) / n,
+            k,
+        }
+    }
+
+    /// Reduces `x < 2^(2k)` modulo `n`. The estimated quotient is at most two
+    /// below the real one, HAC algorithm 14.42.
+    fn reduce(&self, x: &BigUint) -> BigUint {
+        let q = ((x >> (self.k - 1)) * &self.mu) >> (self.k + 1);
+        let mut r = x - q * &self.n;
+        while r >= self.n {
+            r -= &self.n;
+        }
+        r
+    }
+
+    /// Computes `base^exp mod n` like `pow_mod_small`.
+    pub(crate) fn pow_mod_small(&self, base: &BigUint, exp: u64) -> BigUint {
+        if self.n.is_one() {
+            return BigUint::zero();
+        }
+        if exp == 0 {
+            return BigUint::one();
+        }
+
+        let base = if base < &self.n {
+            base.clone()
+        } else {
+            base % &self.n
+        };
+        let mut acc = base.clone();
+        for i in (0..63 - exp.leading_zeros()).rev() {
+            acc = self.reduce(&(&acc * &acc));
+            if exp >> i & 1 == 1 {
+                acc = self.reduce(&(acc * &base));
+            }
+        }
+        acc
+    }
+}
+
+/// Public exponentiations modulo the modulus `self` was set up for use
+/// Barrett reduction, everything else is left to `num-bigint`.
+impl ModPow for Barrett {
+    fn modpow(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+        NumBigint.modpow(base, exp, modulus)
+    }
+
+    fn modpow_public(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+        match exp.to_u64() {
+            Some(exp) if *modulus == self.n => self.pow_mod_small(base, exp),
+            _ => NumBigint.modpow_public(base, exp, modulus),
+        }
+    }
 }
 
 /// Performs raw RSA decryption with no padding, resulting in a plaintext `BigUint`.
 /// Peforms RSA blinding if an `Rng` is passed.
 #[inline]
 pub fn decrypt<R: Rng>(
+    rng: Option<&mut R>,
+    priv_key: &RSAPrivateKey,
+    c: &BigUint,
+) -> Result<BigUint> {
+    decrypt_with(&NumBigint, rng, priv_key, c)
+}
+
+/// Raw RSA decryption like [`decrypt`], exponentiating with `backend`.
+fn decrypt_with<R: Rng, M: ModPow>(
+    backend: &M,
     mut rng: Option<&mut R>,
     priv_key: &RSAPrivateKey,
     c: &BigUint,
@@ -31,28 +180,51 @@ pub fn decrypt<R: Rng>(
         return Err(Error::Decryption);
     }
 
+    let precomputed = priv_key.precomputed_values();
+    let _span = debug_span!(
+        "rsa_private_op",
+        n_bits = priv_key.n_bits(),
+        crt = precomputed.is_some(),
+        blinded = rng.is_some()
+    );
     let mut ir = None;
 
     let c = if let Some(ref mut rng) = rng {
-        let (blinded, unblinder) = blind(rng, priv_key, c);
+        #[cfg(feature = "std")]
+        let pooled = priv_key
+            .blinding_pool
+            .as_ref()
+            .and_then(|pool| pool.blind(priv_key, c));
+        #[cfg(not(feature = "std"))]
+        let pooled = None;
+
+        let (blinded, unblinder) = match pooled {
+            Some(pooled) => pooled,
+            None => blind_with(backend, rng, priv_key, c),
+        };
         ir = Some(unblinder);
         Cow::Owned(blinded)
     } else {
         Cow::Borrowed(c)
     };
 
-    let m = match priv_key.precomputed {
-        None => c.modpow(priv_key.d(), priv_key.n()),
-        Some(ref precomputed) => {
+    let m = match precomputed {
+        None => backend.modpow(&c, priv_key.d(), priv_key.n()),
+        Some(precomputed) => {
             // We have the precalculated values needed for the CRT.
 
             let p = &priv_key.primes()[0];
             let q = &priv_key.primes()[1];
 
-            let mut m = c.modpow(&precomputed.dp, p).into_bigint().unwrap();
-            let mut m2 = c.modpow(&precomputed.dq, q).into_bigint().unwrap();
-
-            m -= &m2;
+            let mut m = recombine(
+                backend.modpow(&c, &precomputed.dp, p),
+                backend.modpow(&c, &precomputed.dq, q),
+                p,
+                q,
+                &precomputed.qinv,
+            )
+            .into_bigint()
+            .unwrap();
 
             let mut primes: Vec<_> = priv_key
                 .primes()
@@ -61,18 +233,15 @@ pub fn decrypt<R: Rng>(
                 .map(Option::unwrap)
                 .collect();
 
-            while m.is_negative() {
-                m += &primes[0];
-            }
-            m *= &precomputed.qinv;
-            m %= &primes[0];
-            m *= &primes[1];
-            m += &m2;
-
-            let mut c = c.into_owned().into_bigint().unwrap();
+            let mut c = c.into_owned();
             for (i, value) in precomputed.crt_values.iter().enumerate() {
                 let prime = &primes[2 + i];
-                m2 = c.modpow(&value.exp, prime);
+                let mut exp = value.exp.to_biguint().expect("CRT exponents are positive");
+                let mut m2 = backend
+                    .modpow(&c, &exp, &priv_key.primes()[2 + i])
+                    .into_bigint()
+                    .unwrap();
+                exp.zeroize();
                 m2 -= &m;
                 m2 *= &value.coeff;
                 m2 %= prime;
@@ -81,6 +250,7 @@ pub fn decrypt<R: Rng>(
                 }
                 m2 *= &value.r;
                 m += &m2;
+                m2.zeroize();
             }
 
             // clear tmp values
@@ -89,7 +259,6 @@ pub fn decrypt<R: Rng>(
             }
             primes.clear();
             c.zeroize();
-            m2.zeroize();
 
             m.into_biguint().expect("failed to decrypt")
         }
@@ -104,6 +273,31 @@ pub fn decrypt<R: Rng>(
     }
 }
 
+/// Garner's recombination `m = m2 + q * ((m1 - m2) * qInv mod p)` for the
+/// two-prime CRT.
+///
+/// `m1 - m2` would borrow depending on the secret residues, so `p` is added
+/// unconditionally first: `m1 + p - (m2 mod p)` is always positive and the
+/// result of the subtraction never depends on which residue is larger.
+fn recombine(mut m1: BigUint, mut m2: BigUint, p: &BigUint, q: &BigUint, qinv: &BigInt) -> BigUint {
+    let mut qinv = qinv.to_biguint().expect("qinv is reduced mod p");
+    let mut m2_mod_p = &m2 % p;
+
+    let mut h = &m1 + p;
+    h -= &m2_mod_p;
+    h *= &qinv;
+    h %= p;
+    h *= q;
+    h += &m2;
+
+    m1.zeroize();
+    m2.zeroize();
+    m2_mod_p.zeroize();
+    qinv.zeroize();
+
+    h
+}
+
 /// Performs RSA decryption, resulting in a plaintext `BigUint`.
 /// Peforms RSA blinding if an `Rng` is passed.
 /// This will also check for errors in the CRT computation.
@@ -113,54 +307,304 @@ pub fn decrypt_and_check<R: Rng>(
     priv_key: &RSAPrivateKey,
     c: &BigUint,
 ) -> Result<BigUint> {
-    let m = decrypt(rng, priv_key, c)?;
+    decrypt_and_check_with(&NumBigint, rng, priv_key, c)
+}
+
+/// Performs RSA decryption like [`decrypt_and_check`], doing every
+/// exponentiation, those of the blinding and the consistency check included,
+/// with `backend`.
+pub fn decrypt_and_check_with<R: Rng, M: ModPow>(
+    backend: &M,
+    rng: Option<&mut R>,
+    priv_key: &RSAPrivateKey,
+    c: &BigUint,
+) -> Result<BigUint> {
+    let monitor = priv_key.fault_monitor.as_ref();
+    if monitor.map(FaultMonitor::is_compromised) == Some(true) {
+        return Err(Error::FaultDetected);
+    }
+
+    let m = decrypt_with(backend, rng, priv_key, c)?;
 
     // In order to defend against errors in the CRT computation, m^e is
     // calculated, which should match the original ciphertext.
-    let check = encrypt(priv_key, &m);
+    let check = encrypt_with(backend, priv_key, &m);
 
     if c != &check {
-        return Err(Error::Internal);
+        debug_event!("private key operation failed the consistency check");
+        // The fault may sit in a pooled blinding pair, where it would break
+        // every later operation using it, so start over with fresh ones.
+        #[cfg(feature = "std")]
+        {
+            if let Some(pool) = priv_key.blinding_pool.as_ref() {
+                pool.discard();
+            }
+        }
+        return Err(monitor.map_or(Error::Internal, FaultMonitor::record_fault));
     }
 
+    if let Some(monitor) = monitor {
+        monitor.record_success();
+    }
     Ok(m)
 }
 
 /// Returns the blinded c, along with the unblinding factor.
+#[cfg_attr(not(feature = "expose-internals"), allow(dead_code))]
 pub fn blind<R: Rng, K: PublicKeyParts>(rng: &mut R, key: &K, c: &BigUint) -> (BigUint, BigUint) {
+    blind_with(&NumBigint, rng, key, c)
+}
+
+fn blind_with<R: Rng, K: PublicKeyParts, M: ModPow>(
+    backend: &M,
+    rng: &mut R,
+    key: &K,
+    c: &BigUint,
+) -> (BigUint, BigUint) {
     // Blinding involves multiplying c by r^e.
     // Then the decryption operation performs (m^e * r^e)^d mod n
     // which equals mr mod n. The factor of r can then be removed
     // by multiplying by the multiplicative inverse of r.
 
+    let (mut rpowe, unblinder) = blinding_pair(backend, rng, key);
+    let mut c = c * &rpowe;
+    c %= key.n();
+
+    rpowe.zeroize();
+
+    (c, unblinder)
+}
+
+/// Draws a random unit r and returns `(r^e mod n, r^-1 mod n)`.
+fn blinding_pair<R: Rng, K: PublicKeyParts, M: ModPow>(
+    backend: &M,
+    rng: &mut R,
+    key: &K,
+) -> (BigUint, BigUint) {
     let mut r: BigUint;
-    let mut ir: Option<BigInt>;
     let unblinder;
     loop {
         r = rng.gen_biguint_below(key.n());
         if r.is_zero() {
             r = BigUint::one();
         }
-        ir = r.clone().mod_inverse(key.n());
-        if let Some(ir) = ir {
-            if let Some(ub) = ir.into_biguint() {
-                unblinder = ub;
-                break;
+        // r is fresh for every operation, so keep it away from the
+        // variable-time extended Euclidean algorithm.
+        if let Some(ir) = mod_inverse_blinded(rng, &r, key.n()) {
+            unblinder = ir;
+            break;
+        }
+    }
+
+    let rpowe = encrypt_with(backend, key, &r); // N != 0
+    r.zeroize();
+
+    (rpowe, unblinder)
+}
+
+/// A pool of blinding pairs `(r^e, r^-1)`, see `RSAPrivateKey::enable_blinding_pool`.
+///
+/// A pair is taken out of the pool for every blinded operation and put back
+/// squared, which is again a valid pair for `r^2`. That costs two modular
+/// multiplications instead of the exponentiation and inversion needed for a
+/// fresh r. If more operations run concurrently than the pool holds pairs,
+/// the extra ones fall back to fresh blinding.
+#[cfg(feature = "std")]
+pub(crate) struct BlindingPool {
+    pairs: std::sync::Mutex<Vec<(BigUint, BigUint)>>,
+}
+
+#[cfg(feature = "std")]
+impl BlindingPool {
+    pub(crate) fn new<R: Rng, K: PublicKeyParts>(rng: &mut R, key: &K, size: usize) -> Self {
+        let pairs = (0..size)
+            .map(|_| blinding_pair(&NumBigint, rng, key))
+            .collect();
+        BlindingPool {
+            pairs: std::sync::Mutex::new(pairs),
+        }
+    }
+
+    /// Blinds `c` with a pair from the pool, or returns `None` if the pool is
+    /// exhausted.
+    fn blind<K: PublicKeyParts>(&self, key: &K, c: &BigUint) -> Option<(BigUint, BigUint)> {
+        let (mut rpowe, mut unblinder) = self.pairs.lock().ok()?.pop()?;
+
+        let mut blinded = c * &rpowe;
+        blinded %= key.n();
+        let result = (blinded, unblinder.clone());
+
+        rpowe = (&rpowe * &rpowe) % key.n();
+        unblinder = (&unblinder * &unblinder) % key.n();
+        match self.pairs.lock() {
+            Ok(mut pairs) => pairs.push((rpowe, unblinder)),
+            Err(_) => {
+                rpowe.zeroize();
+                unblinder.zeroize();
+            }
+        }
+
+        Some(result)
+    }
+
+    /// Drops and zeroizes all pairs, later operations use fresh blinding.
+    fn discard(&self) {
+        if let Ok(mut pairs) = self.pairs.lock() {
+            for (rpowe, unblinder) in pairs.iter_mut() {
+                rpowe.zeroize();
+                unblinder.zeroize();
             }
+            pairs.clear();
         }
     }
 
-    let c = {
-        let mut rpowe = r.modpow(key.e(), key.n()); // N != 0
-        let mut c = c * &rpowe;
-        c %= key.n();
+    pub(crate) fn pairs(&self) -> Vec<(BigUint, BigUint)> {
+        match self.pairs.lock() {
+            Ok(pairs) => pairs.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
 
-        rpowe.zeroize();
+#[cfg(feature = "std")]
+impl Clone for BlindingPool {
+    fn clone(&self) -> Self {
+        BlindingPool {
+            pairs: std::sync::Mutex::new(self.pairs()),
+        }
+    }
+}
 
-        c
-    };
+#[cfg(feature = "std")]
+impl core::fmt::Debug for BlindingPool {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("BlindingPool").finish()
+    }
+}
 
-    (c, unblinder)
+#[cfg(feature = "std")]
+impl Zeroize for BlindingPool {
+    fn zeroize(&mut self) {
+        let pairs = match self.pairs.get_mut() {
+            Ok(pairs) => pairs,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for (rpowe, unblinder) in pairs.iter_mut() {
+            rpowe.zeroize();
+            unblinder.zeroize();
+        }
+        pairs.clear();
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for BlindingPool {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Counts consecutive private key operations that fail the consistency check
+/// of [`decrypt_and_check`].
+///
+/// A single failure may be a glitch, a run of them suggests someone is
+/// injecting faults to recover the primes from faulty CRT results, and each
+/// attempt needs a fault. Once `threshold` failures follow each other, the
+/// operation fails with `Error::FaultDetected` and, with `mark_compromised`,
+/// so does every later operation with the key.
+pub(crate) struct FaultMonitor {
+    threshold: usize,
+    mark_compromised: bool,
+    consecutive: AtomicUsize,
+    compromised: AtomicBool,
+}
+
+impl FaultMonitor {
+    pub(crate) fn new(threshold: usize, mark_compromised: bool) -> Self {
+        FaultMonitor {
+            threshold: core::cmp::max(threshold, 1),
+            mark_compromised,
+            consecutive: AtomicUsize::new(0),
+            compromised: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn is_compromised(&self) -> bool {
+        self.compromised.load(Ordering::SeqCst)
+    }
+
+    /// Records a failed check and returns the error to report for it.
+    fn record_fault(&self) -> Error {
+        let faults = self.consecutive.fetch_add(1, Ordering::SeqCst) + 1;
+        if faults < self.threshold {
+            return Error::Internal;
+        }
+        if self.mark_compromised {
+            self.compromised.store(true, Ordering::SeqCst);
+        }
+        Error::FaultDetected
+    }
+
+    fn record_success(&self) {
+        self.consecutive.store(0, Ordering::SeqCst);
+    }
+}
+
+impl Clone for FaultMonitor {
+    fn clone(&self) -> Self {
+        FaultMonitor {
+            threshold: self.threshold,
+            mark_compromised: self.mark_compromised,
+            consecutive: AtomicUsize::new(self.consecutive.load(Ordering::SeqCst)),
+            compromised: AtomicBool::new(self.is_compromised()),
+        }
+    }
+}
+
+impl core::fmt::Debug for FaultMonitor {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("FaultMonitor")
+            .field("threshold", &self.threshold)
+            .field("compromised", &self.is_compromised())
+            .finish()
+    }
+}
+
+/// Computes the inverse of `a` modulo `n` without passing `a` itself to the
+/// variable-time extended Euclidean algorithm.
+///
+/// `a` is multiplied by a random unit `b` first, so the inversion only sees
+/// `a * b mod n`, which is independent of `a`; the result is then
+/// `(a * b)^-1 * b`. The surrounding `BigUint` arithmetic is not constant
+/// time, but its timing depends only on the operand sizes.
+///
+/// Returns `None` if `a` is not invertible modulo `n`.
+pub fn mod_inverse_blinded<R: Rng>(rng: &mut R, a: &BigUint, n: &BigUint) -> Option<BigUint> {
+    if n <= &BigUint::one() {
+        return None;
+    }
+
+    loop {
+        let mut b = rng.gen_biguint_below(n);
+        let mut ab = (a * &b) % n;
+
+        let inverse = ab
+            .clone()
+            .mod_inverse(n)
+            .and_then(|iab| iab.into_biguint())
+            .map(|iab| (iab * &b) % n);
+        // If `a * b` is not invertible, either `a` or `b` shares a factor with
+        // `n`. Only `b`, which is unrelated to `a`, is checked, and a new one
+        // is drawn if it was the culprit.
+        let retry = inverse.is_none() && !b.gcd(n).is_one();
+
+        b.zeroize();
+        ab.zeroize();
+
+        if !retry {
+            return inverse;
+        }
+    }
 }
 
 /// Given an m and and unblinding factor, unblind the m.
@@ -169,15 +613,284 @@ pub fn unblind(key: impl PublicKeyParts, m: &BigUint, unblinder: &BigUint) -> Bi
 }
 
 /// Returns a new vector of the given length, with 0s left padded.
+///
+/// Panics if `input` is longer than `size`, callers check the length of
+/// values that do not come from this crate.
 #[inline]
 pub fn left_pad(input: &[u8], size: usize) -> Vec<u8> {
-    let n = if input.len() > size {
+    assert!(
+        input.len() <= size,
+        "left_pad: input longer than {} bytes",
         size
-    } else {
-        input.len()
-    };
+    );
 
     let mut out = vec![0u8; size];
-    out[size - n..].copy_from_slice(input);
+    out[size - input.len()..].copy_from_slice(input);
     out
 }
+
+/// Returns whether the big-endian integer `x` is smaller than `n`.
+///
+/// The running time only depends on the lengths of `x` and `n`, not on their
+/// values, so this is also fine for secret message representatives.
+pub(crate) fn ct_less_than(x: &[u8], n: &BigUint) -> Choice {
+    let n = n.to_bytes_be();
+    let len = core::cmp::max(x.len(), n.len());
+    let byte_at = |bytes: &[u8], i: usize| match (i + bytes.len()).checked_sub(len) {
+        Some(j) => bytes[j],
+        None => 0,
+    };
+
+    let mut lt = Choice::from(0);
+    let mut eq = Choice::from(1);
+    for i in 0..len {
+        let (a, b) = (byte_at(x, i), byte_at(&n, i));
+        // a < b exactly when a - b borrows.
+        let borrow = ((a as u16).wrapping_sub(b as u16) >> 15) as u8;
+        lt |= eq & Choice::from(borrow);
+        eq &= a.ct_eq(&b);
+    }
+    lt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_traits::FromPrimitive;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_mod_inverse_blinded() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for i in 0..1000 {
+            let n = rng.gen_biguint(8 + (i % 256)) | BigUint::one();
+            if n.is_one() {
+                continue;
+            }
+            let a = rng.gen_biguint_below(&n);
+
+            let expected = a.clone().mod_inverse(&n).and_then(|ia| ia.into_biguint());
+            assert_eq!(
+                mod_inverse_blinded(&mut rng, &a, &n),
+                expected,
+                "{} mod {}",
+                a,
+                n
+            );
+        }
+
+        let n = BigUint::from_u64(15).unwrap();
+        assert_eq!(
+            mod_inverse_blinded(&mut rng, &BigUint::from_u64(5).unwrap(), &n),
+            None
+        );
+        assert_eq!(mod_inverse_blinded(&mut rng, &BigUint::zero(), &n), None);
+        assert_eq!(
+            mod_inverse_blinded(&mut rng, &BigUint::from_u64(7).unwrap(), &n),
+            Some(BigUint::from_u64(13).unwrap())
+        );
+        assert_eq!(
+            mod_inverse_blinded(&mut rng, &BigUint::one(), &BigUint::one()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_blinding_pool() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut key = RSAPrivateKey::new(&mut rng, 256).unwrap();
+        key.enable_blinding_pool(&mut rng, 2);
+
+        for _ in 0..100 {
+            let c = rng.gen_biguint_below(key.n());
+            let m = decrypt::<StdRng>(None, &key, &c).unwrap();
+            assert_eq!(decrypt(Some(&mut rng), &key, &c).unwrap(), m);
+        }
+
+        // Every pair must still satisfy (r^e) * (r^-1)^e = 1 after squaring.
+        let pairs = key.blinding_pool.as_ref().unwrap().pairs();
+        assert_eq!(pairs.len(), 2);
+        for (rpowe, unblinder) in &pairs {
+            assert_ne!(rpowe, &BigUint::one());
+            assert!((rpowe * unblinder.modpow(key.e(), key.n()) % key.n()).is_one());
+        }
+
+        let cloned = key.clone();
+        assert_eq!(cloned.blinding_pool.as_ref().unwrap().pairs(), pairs);
+
+        key.disable_blinding_pool();
+        assert!(key.blinding_pool.is_none());
+    }
+
+    #[test]
+    fn test_pow_mod_small_matches_modpow() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let exps = [0u64, 1, 2, 3, 17, 65537, 0xdead_beef, !0];
+
+        for bits in &[8, 64, 65, 512, 2048] {
+            for i in 0..20 {
+                let mut modulus = rng.gen_biguint(*bits);
+                if i % 2 == 0 {
+                    modulus |= BigUint::one();
+                }
+                if modulus.is_zero() {
+                    continue;
+                }
+                let base = rng.gen_biguint(*bits + 8);
+                let barrett = Barrett::new(&modulus);
+                for &exp in exps.iter().chain(&[rng.gen::<u64>()]) {
+                    let expected = base.modpow(&BigUint::from_u64(exp).unwrap(), &modulus);
+                    assert_eq!(pow_mod_small(&base, exp, &modulus), expected);
+                    assert_eq!(barrett.pow_mod_small(&base, exp), expected);
+                }
+            }
+        }
+        assert!(pow_mod_small(&BigUint::from_u64(5).unwrap(), 0, &BigUint::one()).is_zero());
+        let barrett = Barrett::new(&BigUint::one());
+        assert!(barrett
+            .pow_mod_small(&BigUint::from_u64(5).unwrap(), 0)
+            .is_zero());
+    }
+
+    #[test]
+    fn test_barrett_reduce() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for bits in &[2, 63, 64, 65, 1024] {
+            // The smallest and largest moduli of the size, and random ones.
+            let low = BigUint::one() << (*bits - 1);
+            let high = (BigUint::one() << *bits) - BigUint::one();
+            let mut moduli = vec![low.clone(), high];
+            moduli.extend((0..8).map(|_| rng.gen_biguint(*bits - 1) | &low));
+            for n in moduli {
+                let barrett = Barrett::new(&n);
+                let max = (BigUint::one() << (2 * *bits)) - BigUint::one();
+                for x in &[
+                    BigUint::zero(),
+                    &n - BigUint::one(),
+                    n.clone(),
+                    &n * &n,
+                    max,
+                ] {
+                    assert_eq!(barrett.reduce(x), x % &n);
+                }
+                for _ in 0..16 {
+                    let x = rng.gen_biguint(2 * *bits);
+                    assert_eq!(barrett.reduce(&x), &x % &n);
+                }
+            }
+        }
+    }
+
+    /// Counts the exponentiations it does with `NumBigint`.
+    #[derive(Default)]
+    struct Counting {
+        secret: core::cell::Cell<usize>,
+        public: core::cell::Cell<usize>,
+    }
+
+    impl ModPow for Counting {
+        fn modpow(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+            self.secret.set(self.secret.get() + 1);
+            NumBigint.modpow(base, exp, modulus)
+        }
+
+        fn modpow_public(&self, base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+            self.public.set(self.public.get() + 1);
+            NumBigint.modpow_public(base, exp, modulus)
+        }
+    }
+
+    #[test]
+    fn test_mod_pow_backends() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for &nprimes in &[2, 3] {
+            let key = crate::algorithms::generate_multi_prime_key(&mut rng, nprimes, 256).unwrap();
+            let barrett = Barrett::new(key.n());
+            for _ in 0..10 {
+                let c = rng.gen_biguint_below(key.n());
+                let m = decrypt_and_check::<StdRng>(None, &key, &c).unwrap();
+
+                let counting = Counting::default();
+                assert_eq!(
+                    decrypt_and_check_with(&counting, Some(&mut rng), &key, &c).unwrap(),
+                    m
+                );
+                // One exponentiation per prime, then r^e and the check.
+                assert_eq!(counting.secret.get(), nprimes);
+                assert_eq!(counting.public.get(), 2);
+
+                assert_eq!(
+                    decrypt_and_check_with(&barrett, Some(&mut rng), &key, &c).unwrap(),
+                    m
+                );
+                assert_eq!(encrypt_with(&barrett, &key, &m), c);
+                assert_eq!(encrypt_with(&counting, &key, &m), c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crt_matches_plain_decrypt() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for bits in &[64, 128, 256] {
+            let key = RSAPrivateKey::new(&mut rng, *bits).unwrap();
+            assert!(key.precomputed_values().is_some());
+            let mut plain = key.clone();
+            plain.precomputed = None.into();
+
+            for _ in 0..1000 {
+                let c = rng.gen_biguint_below(key.n());
+                let m = decrypt::<StdRng>(None, &key, &c).unwrap();
+                assert_eq!(m, decrypt::<StdRng>(None, &plain, &c).unwrap(), "c = {}", c);
+                assert!(&m < key.n());
+            }
+        }
+
+        // Residues on both sides of the borrow, including m1 == m2.
+        let key = RSAPrivateKey::new(&mut rng, 128).unwrap();
+        let p = &key.primes()[0];
+        let q = &key.primes()[1];
+        let qinv = key.precomputed_values().unwrap().qinv.clone();
+        for &(m1, m2) in &[(0u64, 0u64), (0, 1), (1, 0), (5, 5), (3, 9), (9, 3)] {
+            let m1 = BigUint::from_u64(m1).unwrap();
+            let m2 = BigUint::from_u64(m2).unwrap();
+            let m = recombine(m1.clone(), m2.clone(), p, q, &qinv);
+            assert_eq!(&m % p, m1);
+            assert_eq!(&m % q, m2);
+        }
+    }
+
+    #[test]
+    fn test_left_pad() {
+        assert_eq!(left_pad(&[1, 2], 4), [0, 0, 1, 2]);
+        assert_eq!(left_pad(&[1, 2], 2), [1, 2]);
+        assert!(left_pad(&[], 0).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "input longer")]
+    fn test_left_pad_rejects_long_input() {
+        left_pad(&[0, 1, 2], 2);
+    }
+
+    #[test]
+    fn test_ct_less_than() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for i in 0..1000 {
+            let n = rng.gen_biguint(1 + (i % 200)) | BigUint::one();
+            let x = rng.gen_biguint(1 + (i % 211));
+            for x in [x.clone(), n.clone(), &n - 1u32, &n + 1u32].iter() {
+                let expected = *x < n;
+                assert_eq!(bool::from(ct_less_than(&x.to_bytes_be(), &n)), expected);
+                // Leading zeroes do not change the result.
+                let padded = left_pad(&x.to_bytes_be(), x.bits().div_ceil(8) + 3);
+                assert_eq!(bool::from(ct_less_than(&padded, &n)), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! The RSASSA-PSS algorithms of JSON Web Signatures, RFC 7518 section 3.5.
+//!
+//! `PS256`, `PS384` and `PS512` use SHA-256, SHA-384 and SHA-512 for both
+//! the message hash and MGF1, with a salt as long as the hash. The signing
+//! input is `BASE64URL(header) || '.' || BASE64URL(payload)`, building it and
+//! encoding the signature is left to the JOSE library.
+//!
+//! Since the salt length is fixed, [`verify`] asks for exactly that length
+//! instead of recovering it from the signature. That rejects signatures RFC
+//! 7518 does not allow and skips scanning the data block for the end of the
+//! padding, although next to the modular exponentiation the scan is too
+//! cheap to measure, even for 4096 bit keys.
+
+use alloc::vec::Vec;
+
+use digest::{Digest, DynDigest};
+use rand::Rng;
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::errors::{Error, Result};
+use crate::key::{PublicKey, RSAPrivateKey};
+use crate::pss;
+
+/// A JWS `alg` value for RSASSA-PSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PssAlgorithm {
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256.
+    PS256,
+    /// RSASSA-PSS using SHA-384 and MGF1 with SHA-384.
+    PS384,
+    /// RSASSA-PSS using SHA-512 and MGF1 with SHA-512.
+    PS512,
+}
+
+impl PssAlgorithm {
+    /// The `alg` header parameter value.
+    pub fn name(self) -> &'static str {
+        match self {
+            PssAlgorithm::PS256 => "PS256",
+            PssAlgorithm::PS384 => "PS384",
+            PssAlgorithm::PS512 => "PS512",
+        }
+    }
+
+    /// Looks up an `alg` header parameter value, failing with
+    /// `Error::InvalidPaddingScheme` for anything but the three PSS
+    /// algorithms.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "PS256" => Ok(PssAlgorithm::PS256),
+            "PS384" => Ok(PssAlgorithm::PS384),
+            "PS512" => Ok(PssAlgorithm::PS512),
+            _ => Err(Error::InvalidPaddingScheme),
+        }
+    }
+
+    /// Length in bytes of the hash, and thus of the salt.
+    pub fn salt_len(self) -> usize {
+        match self {
+            PssAlgorithm::PS256 => 32,
+            PssAlgorithm::PS384 => 48,
+            PssAlgorithm::PS512 => 64,
+        }
+    }
+}
+
+/// Signs the JWS signing input `signing_input` with `alg`, using `rng` for
+/// the salt and for blinding.
+pub fn sign<R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    alg: PssAlgorithm,
+    signing_input: &[u8],
+) -> Result<Vec<u8>> {
+    match alg {
+        PssAlgorithm::PS256 => sign_with::<Sha256, _>(rng, priv_key, signing_input),
+        PssAlgorithm::PS384 => sign_with::<Sha384, _>(rng, priv_key, signing_input),
+        PssAlgorithm::PS512 => sign_with::<Sha512, _>(rng, priv_key, signing_input),
+    }
+}
+
+/// Verifies the signature `sig` of the JWS signing input `signing_input`
+/// with `alg`. Signatures with any salt length other than that of the hash
+/// are rejected, as RFC 7518 requires.
+pub fn verify<PK: PublicKey>(
+    pub_key: &PK,
+    alg: PssAlgorithm,
+    signing_input: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    match alg {
+        PssAlgorithm::PS256 => verify_with::<Sha256, _>(pub_key, signing_input, sig),
+        PssAlgorithm::PS384 => verify_with::<Sha384, _>(pub_key, signing_input, sig),
+        PssAlgorithm::PS512 => verify_with::<Sha512, _>(pub_key, signing_input, sig),
+    }
+}
+
+fn sign_with<H: Digest + DynDigest, R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    signing_input: &[u8],
+) -> Result<Vec<u8>> {
+    pss::sign_pss_hash_len_salt::<H, _, _>(priv_key, rng, &H::digest(signing_input))
+}
+
+fn verify_with<H: Digest + DynDigest, PK: PublicKey>(
+    pub_key: &PK,
+    signing_input: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    pss::verify_pss_hash_len_salt::<_, H>(pub_key, &H::digest(signing_input), sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, PublicKeyParts};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const ALGORITHMS: [PssAlgorithm; 3] = [
+        PssAlgorithm::PS256,
+        PssAlgorithm::PS384,
+        PssAlgorithm::PS512,
+    ];
+
+    #[test]
+    fn test_sign_verify() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_2048().clone();
+        let pub_key = priv_key.to_public_key();
+        let input = b"eyJhbGciOiJQUzI1NiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0";
+
+        for &alg in ALGORITHMS.iter() {
+            assert_eq!(PssAlgorithm::from_name(alg.name()).unwrap(), alg);
+
+            let sig = sign(&mut rng, &priv_key, alg, input).unwrap();
+            assert_eq!(sig.len(), pub_key.size());
+            verify(&pub_key, alg, input, &sig).unwrap();
+            assert!(verify(&pub_key, alg, b"eyJhbGciOiJQUzI1NiJ9.e30", &sig).is_err());
+            for &other in ALGORITHMS.iter().filter(|&&other| other != alg) {
+                assert!(verify(&pub_key, other, input, &sig).is_err());
+            }
+        }
+        assert!(PssAlgorithm::from_name("RS256").is_err());
+    }
+}
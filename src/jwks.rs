@@ -0,0 +1,553 @@
+//! Verifying JSON Web Signatures against a JSON Web Key Set, RFC 7517
+//! section 5, as published by OpenID Connect providers.
+//!
+//! Only the RSA keys of a set are kept, keys of other types are skipped. A
+//! JWS is verified with the key its protected header names by `kid`, using
+//! `RS256`, `RS384` or `RS512` (PKCS#1 v1.5) or the PSS algorithms of
+//! [`jose`](crate::jose).
+//!
+//! The crate has no JSON dependency, so the documents are read by a small
+//! strict parser that rejects duplicate member names and nesting deeper than
+//! a key set or a header ever needs. The module is only built with the
+//! opt-in `jwks` feature.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::errors::{Error, Result};
+use crate::hash::Hash;
+use crate::jose::{self, PssAlgorithm};
+use crate::key::{PublicKey, RSAPublicKey};
+use crate::padding::PaddingScheme;
+
+/// Deepest nesting of arrays and objects accepted in a document.
+const MAX_DEPTH: usize = 16;
+
+/// A JSON Web Key Set holding RSA public keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+/// A key of the set with the parameters used to select it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Jwk {
+    kid: Option<String>,
+    alg: Option<String>,
+    key: RSAPublicKey,
+}
+
+impl Jwks {
+    /// Parses a JWKS document, `{"keys": [...]}`.
+    ///
+    /// Keys whose `kty` is not `RSA` and keys whose `use` is not `sig` are
+    /// skipped. Fails with `Error::ParseError` on malformed JSON or RSA keys
+    /// without valid `n` and `e`, and with the error of `RSAPublicKey::new`
+    /// for keys it rejects.
+    pub fn from_json(json: &str) -> Result<Jwks> {
+        let document = Parser::parse(json)?;
+        let entries = match document.member("keys") {
+            Some(Json::Array(entries)) => entries,
+            _ => return Err(parse_error("missing keys array")),
+        };
+
+        let mut keys = Vec::new();
+        for entry in entries {
+            if !matches_str(entry.member("kty"), "RSA") {
+                continue;
+            }
+            if entry.member("use").is_some() && !matches_str(entry.member("use"), "sig") {
+                continue;
+            }
+            let n = entry.base64_member("n")?;
+            let e = entry.base64_member("e")?;
+            keys.push(Jwk {
+                kid: entry.string_member("kid")?,
+                alg: entry.string_member("alg")?,
+                key: RSAPublicKey::new(n, e)?,
+            });
+        }
+
+        Ok(Jwks { keys })
+    }
+
+    /// Number of RSA keys in the set.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether the set holds no RSA keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the first key with the key id `kid`.
+    pub fn find_by_kid(&self, kid: &str) -> Option<&RSAPublicKey> {
+        self.find(Some(kid)).map(|jwk| &jwk.key)
+    }
+
+    /// Verifies the JWS signature `sig` of the base64url encoded protected
+    /// header `header` and payload `payload`, the first two parts of a compact
+    /// serialization.
+    ///
+    /// The key is the one named by the header's `kid`; a header without `kid`
+    /// is only accepted if the set holds a single key. A key with an `alg`
+    /// parameter only verifies that algorithm. Fails with
+    /// `Error::ParseError` on a malformed header,
+    /// `Error::InvalidPaddingScheme` on an unsupported `alg` and
+    /// `Error::Verification` if no key matches, the header has a `crit`
+    /// parameter or the signature is invalid.
+    pub fn verify_jws(&self, header: &str, payload: &str, sig: &[u8]) -> Result<()> {
+        let decoded = base64::decode_config(header, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| parse_error("header is not base64url"))?;
+        let json =
+            core::str::from_utf8(&decoded).map_err(|_| parse_error("header is not UTF-8"))?;
+        let parsed = match Parser::parse(json)? {
+            parsed @ Json::Object(_) => parsed,
+            _ => return Err(parse_error("header is not an object")),
+        };
+
+        // No extensions are understood, so RFC 7515 section 4.1.11 requires
+        // rejecting any that are marked critical.
+        if parsed.member("crit").is_some() {
+            return Err(Error::Verification);
+        }
+        let alg = parsed
+            .string_member("alg")?
+            .ok_or_else(|| parse_error("header has no alg"))?;
+        let kid = parsed.string_member("kid")?;
+
+        let jwk = self
+            .find(kid.as_ref().map(|kid| &kid[..]))
+            .ok_or(Error::Verification)?;
+        if let Some(ref key_alg) = jwk.alg {
+            if *key_alg != alg {
+                return Err(Error::Verification);
+            }
+        }
+
+        let mut signing_input = Vec::with_capacity(header.len() + 1 + payload.len());
+        signing_input.extend_from_slice(header.as_bytes());
+        signing_input.push(b'.');
+        signing_input.extend_from_slice(payload.as_bytes());
+
+        let (hash, hashed) = match alg.as_str() {
+            "RS256" => (Hash::SHA2_256, Sha256::digest(&signing_input).to_vec()),
+            "RS384" => (Hash::SHA2_384, Sha384::digest(&signing_input).to_vec()),
+            "RS512" => (Hash::SHA2_512, Sha512::digest(&signing_input).to_vec()),
+            name => {
+                let alg = PssAlgorithm::from_name(name)?;
+                return jose::verify(&jwk.key, alg, &signing_input, sig);
+            }
+        };
+        jwk.key
+            .verify(PaddingScheme::new_pkcs1v15_sign(Some(hash)), &hashed, sig)
+    }
+
+    /// Returns the key with the key id `kid`, or the only key if `kid` is
+    /// `None`.
+    fn find(&self, kid: Option<&str>) -> Option<&Jwk> {
+        match kid {
+            Some(kid) => self
+                .keys
+                .iter()
+                .find(|jwk| jwk.kid.iter().any(|id| id == kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+    }
+}
+
+fn parse_error(reason: &str) -> Error {
+    Error::ParseError {
+        reason: format!("invalid JWKS or JWS header: {}", reason),
+    }
+}
+
+fn matches_str(value: Option<&Json>, expected: &str) -> bool {
+    match value {
+        Some(Json::String(value)) => value == expected,
+        _ => false,
+    }
+}
+
+/// A parsed JSON value. Numbers and booleans are checked but not kept, no
+/// member read here is one.
+#[derive(Debug)]
+enum Json {
+    Null,
+    Bool,
+    Number,
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    fn member(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.get(name),
+            _ => None,
+        }
+    }
+
+    fn string_member(&self, name: &str) -> Result<Option<String>> {
+        match self.member(name) {
+            None => Ok(None),
+            Some(Json::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(parse_error(&format!("{} is not a string", name))),
+        }
+    }
+
+    fn base64_member(&self, name: &str) -> Result<BigUint> {
+        let value = self
+            .string_member(name)?
+            .ok_or_else(|| parse_error(&format!("RSA key without {}", name)))?;
+        let bytes = base64::decode_config(&value, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| parse_error(&format!("{} is not base64url", name)))?;
+        Ok(BigUint::from_bytes_be(&bytes))
+    }
+}
+
+/// Reads JSON as RFC 8259 defines it.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(json: &'a str) -> Result<Json> {
+        let mut parser = Parser {
+            input: json.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos != parser.input.len() {
+            return Err(parse_error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let byte = self.peek().ok_or_else(|| parse_error("unexpected end"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, literal: &[u8]) -> Result<()> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(parse_error("unexpected character"))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json> {
+        if depth > MAX_DEPTH {
+            return Err(parse_error("nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.expect(b"true").map(|_| Json::Bool),
+            Some(b'f') => self.expect(b"false").map(|_| Json::Bool),
+            Some(b'n') => self.expect(b"null").map(|_| Json::Null),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(parse_error("unexpected character")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json> {
+        self.expect(b"{")?;
+        let mut members = BTreeMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            // RFC 7515 section 4 requires rejecting duplicate header
+            // parameters, the same is done for keys.
+            if members.contains_key(&name) {
+                return Err(parse_error(&format!("duplicate member {}", name)));
+            }
+            self.skip_whitespace();
+            self.expect(b":")?;
+            let value = self.value(depth + 1)?;
+            members.insert(name, value);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => {}
+                b'}' => return Ok(Json::Object(members)),
+                _ => return Err(parse_error("expected , or }")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json> {
+        self.expect(b"[")?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => {}
+                b']' => return Ok(Json::Array(values)),
+                _ => return Err(parse_error("expected , or ]")),
+            }
+        }
+    }
+
+    fn digits(&mut self) -> Result<()> {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(parse_error("invalid number"));
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<Json> {
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits()?;
+        }
+        Ok(Json::Number)
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = (self.next()? as char)
+                .to_digit(16)
+                .ok_or_else(|| parse_error("invalid escape"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect(b"\"")?;
+        let mut out = Vec::new();
+        loop {
+            match self.next()? {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(parse_error("invalid escape")),
+                    };
+                    let mut buf = [0u8; 4];
+                    out.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                byte if byte < 0x20 => return Err(parse_error("control character in string")),
+                byte => out.push(byte),
+            }
+        }
+        // The input is a `str`, and escapes only add whole characters.
+        String::from_utf8(out).map_err(|_| parse_error("invalid UTF-8"))
+    }
+
+    /// Decodes the code point after `\u`, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            self.expect(b"\\u")?;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(parse_error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        core::char::from_u32(code).ok_or_else(|| parse_error("unpaired surrogate"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::{PublicKeyParts, RSAPrivateKey};
+    use crate::test_util;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn b64(bytes: &[u8]) -> String {
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    fn jwk(kid: &str, alg: &str, key: &RSAPrivateKey) -> String {
+        format!(
+            r#"{{"kty": "RSA", "use": "sig", "kid": "{}", "alg": "{}", "n": "{}", "e": "{}",
+                "x5c": [], "exp": 1.5e10, "ext": true}}"#,
+            kid,
+            alg,
+            b64(&key.n().to_bytes_be()),
+            b64(&key.e().to_bytes_be())
+        )
+    }
+
+    fn sample_jwks() -> String {
+        format!(
+            r#"{{"keys": [
+                {{"kty": "EC", "crv": "P-256", "kid": "ec", "x": "AA", "y": "AA"}},
+                {},
+                {}
+            ]}}"#,
+            jwk("first", "PS256", test_util::test_key_1024()),
+            jwk("second", "RS256", test_util::test_key_2048())
+        )
+    }
+
+    #[test]
+    fn test_find_by_kid() {
+        let jwks = Jwks::from_json(&sample_jwks()).unwrap();
+        assert_eq!(jwks.len(), 2);
+        assert_eq!(
+            jwks.find_by_kid("second").unwrap(),
+            &test_util::test_key_2048().to_public_key()
+        );
+        assert!(jwks.find_by_kid("ec").is_none());
+        assert!(jwks.find_by_kid("third").is_none());
+    }
+
+    #[test]
+    fn test_verify_jws() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let jwks = Jwks::from_json(&sample_jwks()).unwrap();
+        let payload = b64(br#"{"iss":"https://issuer.example","sub":"1234567890"}"#);
+
+        let header = b64(br#"{"alg":"RS256","typ":"JWT","kid":"second"}"#);
+        let input = format!("{}.{}", header, payload);
+        let sig = test_util::test_key_2048()
+            .sign(
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                &Sha256::digest(input.as_bytes()),
+            )
+            .unwrap();
+        jwks.verify_jws(&header, &payload, &sig).unwrap();
+        match jwks.verify_jws(&header, &b64(b"{}"), &sig) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let header = b64(br#"{"alg":"PS256","kid":"first"}"#);
+        let input = format!("{}.{}", header, payload);
+        let sig = jose::sign(
+            &mut rng,
+            test_util::test_key_1024(),
+            PssAlgorithm::PS256,
+            input.as_bytes(),
+        )
+        .unwrap();
+        jwks.verify_jws(&header, &payload, &sig).unwrap();
+
+        // The same signature under the other key, an algorithm the key does
+        // not allow, no kid with two keys and a critical extension.
+        for header in &[
+            r#"{"alg":"PS256","kid":"second"}"#,
+            r#"{"alg":"PS384","kid":"first"}"#,
+            r#"{"alg":"PS256"}"#,
+            r#"{"alg":"PS256","kid":"first","crit":["exp"]}"#,
+        ] {
+            match jwks.verify_jws(&b64(header.as_bytes()), &payload, &sig) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result for {}: {:?}", header, other),
+            }
+        }
+
+        let single = format!(
+            r#"{{"keys": [{{"kty": "RSA", "n": "{}", "e": "AQAB"}}]}}"#,
+            b64(&test_util::test_key_1024().n().to_bytes_be())
+        );
+        let jwks = Jwks::from_json(&single).unwrap();
+        jwks.verify_jws(&b64(br#"{"alg":"PS256"}"#), &payload, &sig)
+            .unwrap_err();
+        let header = b64(br#"{"alg":"PS256","kid":"first"}"#);
+        match jwks.verify_jws(&header, &payload, &sig) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match jwks.verify_jws(&b64(br#"{"alg":"none"}"#), &payload, &sig) {
+            Err(Error::InvalidPaddingScheme) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_json() {
+        for json in &[
+            "",
+            "{",
+            r#"{"keys": {}}"#,
+            r#"{"keys": [], "keys": []}"#,
+            r#"{"keys": [], "a": 1, "b": 2, "keys": []}"#,
+            r#"{"keys": []} x"#,
+            r#"{"keys": [{"kty": "RSA", "e": "AQAB"}]}"#,
+            r#"{"keys": [{"kty": "RSA", "n": "AQAB", "e": 3}]}"#,
+            r#"{"keys": ["\ud800"]}"#,
+            r#"{"keys": [01]}"#,
+            "[[[[[[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]]]]]]",
+        ] {
+            match Jwks::from_json(json) {
+                Err(Error::ParseError { .. }) => {}
+                other => panic!("unexpected result for {}: {:?}", json, other),
+            }
+        }
+
+        let jwks = Jwks::from_json(r#" {"keys": [], "note": "café 😀 \"\/"} "#).unwrap();
+        assert!(jwks.is_empty());
+    }
+}
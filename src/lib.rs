@@ -77,6 +77,30 @@ extern crate hex;
 #[cfg(all(test, feature = "serde"))]
 extern crate serde_test;
 
+/// Enters a debug-level `tracing` span that lasts until the returned guard
+/// is dropped. Without the `tracing` feature this expands to nothing.
+///
+/// Only ever pass structural metadata such as lengths, never key material,
+/// salts or messages.
+macro_rules! debug_span {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::debug_span!($($arg)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = core::marker::PhantomData::<()>;
+        guard
+    }};
+}
+
+/// Emits a debug-level `tracing` event. Without the `tracing` feature this
+/// expands to nothing. The same restrictions as for `debug_span!` apply.
+macro_rules! debug_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
 #[cfg(feature = "alloc")]
 pub use num_bigint::BigUint;
 
@@ -96,6 +120,46 @@ pub mod hash;
 #[cfg(feature = "alloc")]
 pub mod padding;
 
+/// RSAES-OAEP encryption.
+#[cfg(feature = "alloc")]
+pub mod oaep;
+
+/// PKCS#1 v1.5 encryption and signatures.
+#[cfg(feature = "alloc")]
+pub mod pkcs1v15;
+
+/// RSASSA-PSS signatures.
+#[cfg(feature = "alloc")]
+pub mod pss;
+
+/// RSASSA-PSS for JSON Web Signatures.
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+pub mod jose;
+
+/// Verifying JSON Web Signatures against a JSON Web Key Set.
+#[cfg(feature = "jwks")]
+pub mod jwks;
+
+/// RSASSA-PSS for the TLS 1.3 signature schemes.
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+pub mod tls;
+
+/// Signatures that carry their hash and padding.
+#[cfg(feature = "alloc")]
+pub mod tagged;
+
+/// Checks for weak public keys.
+#[cfg(feature = "alloc")]
+pub mod policy;
+
+/// PSS signing with salts from a seeded AES CTR_DRBG.
+#[cfg(feature = "aes")]
+pub mod drbg;
+
+/// Deterministic helpers for tests, not for production use.
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
 #[cfg(feature = "pem")]
 pub use pem;
 
@@ -104,16 +168,14 @@ mod encode;
 
 #[cfg(feature = "alloc")]
 mod key;
-#[cfg(feature = "alloc")]
-mod oaep;
 #[cfg(feature = "std")]
 mod parse;
 #[cfg(feature = "alloc")]
-mod pkcs1v15;
-#[cfg(feature = "alloc")]
-mod pss;
-#[cfg(feature = "alloc")]
 mod raw;
+#[cfg(feature = "alloc")]
+mod sizes;
+#[cfg(feature = "openssl")]
+mod openssl_compat;
 
 #[cfg(feature = "std")]
 pub use self::encode::{
@@ -122,7 +184,10 @@ pub use self::encode::{
 #[cfg(feature = "alloc")]
 pub use self::hash::Hash;
 #[cfg(feature = "alloc")]
-pub use self::key::{PublicKey, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+pub use self::key::{
+    validate_representative, KeyInfo, PrivateExponentiation, PrivateKeyInfo, PublicKey,
+    PublicKeyParts, RSAPrivateKey, RSAPublicKey,
+};
 #[cfg(feature = "alloc")]
 pub use self::padding::PaddingScheme;
 
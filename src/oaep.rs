@@ -2,8 +2,10 @@ use rand::Rng;
 use alloc::vec;
 use alloc::vec::Vec;
 use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::format;
 
-use digest::DynDigest;
+use digest::{Digest, DynDigest};
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
 use crate::algorithms::mgf1_xor;
@@ -14,6 +16,10 @@ use crate::key::{self, PrivateKey, PublicKey};
 // TODO: This is the maximum for SHA-1, unclear from the RFC what the values are for other hashing functions.
 const MAX_LABEL_LEN: u64 = 2_305_843_009_213_693_951;
 
+/// Size of the big-endian length prefix in front of every chunk produced by
+/// `encrypt_chunked`.
+const CHUNK_LEN_PREFIX: usize = 4;
+
 /// Encrypts the given message with RSA and the padding
 /// scheme from PKCS#1 OAEP.  The message must be no longer than the
 /// length of the public modulus minus (2+ 2*hash.size()).
@@ -61,6 +67,194 @@ pub fn encrypt<R: Rng, K: PublicKey>(
     pub_key.raw_encryption_primitive(&em, pub_key.size())
 }
 
+/// Encrypts `data` of arbitrary length by splitting it into pieces of the
+/// largest size OAEP with `H` accepts for this key, and encrypting each one
+/// separately without a label.
+///
+/// The output is the concatenation of every ciphertext, each preceded by its
+/// length as a 4-byte big-endian integer. Empty `data` produces a single
+/// chunk holding an empty message.
+///
+/// Each chunk is an independent OAEP ciphertext, so this is weaker than the
+/// usual IND-CCA2 guarantee for the message as a whole: anyone can drop,
+/// reorder or duplicate chunks, or splice in chunks from another message
+/// encrypted to the same key, and `decrypt_chunked` will not notice. The
+/// number of chunks also reveals the approximate length of `data`. Prefer
+/// encrypting a symmetric key and using an authenticated cipher for the
+/// payload; this is only meant for small payloads where that is not an option.
+pub fn encrypt_chunked<R: Rng, H: Digest + DynDigest, K: PublicKey>(
+    rng: &mut R,
+    pub_key: &K,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    key::check_public(pub_key)?;
+
+    let k = pub_key.size();
+    let max_message_len = max_chunk_len::<H, _>(pub_key)?;
+
+    let chunks = data.len().div_ceil(max_message_len);
+    let mut out = Vec::with_capacity(chunks.max(1) * (CHUNK_LEN_PREFIX + k));
+    encrypt_chunks::<_, H, _, _>(rng, pub_key, data, |prefix, ciphertext| {
+        out.extend_from_slice(prefix);
+        out.extend_from_slice(ciphertext);
+        Ok(())
+    })?;
+
+    Ok(out)
+}
+
+/// Encrypts `data` like `encrypt_chunked`, writing each length-prefixed
+/// chunk to `writer` as soon as it is encrypted instead of collecting them.
+///
+/// The output is the same as that of `encrypt_chunked`, and so are its
+/// shortcomings. Failures of `writer` are returned as `Error::EncodeError`.
+#[cfg(feature = "std")]
+pub fn encrypt_to_writer<R: Rng, H: Digest + DynDigest, K: PublicKey, W: std::io::Write>(
+    rng: &mut R,
+    pub_key: &K,
+    data: &[u8],
+    writer: &mut W,
+) -> Result<()> {
+    key::check_public(pub_key)?;
+
+    let write_error = |err: std::io::Error| Error::EncodeError {
+        reason: format!("failed to write ciphertext: {}", err),
+    };
+    encrypt_chunks::<_, H, _, _>(rng, pub_key, data, |prefix, ciphertext| {
+        writer.write_all(prefix).map_err(write_error)?;
+        writer.write_all(ciphertext).map_err(write_error)
+    })
+}
+
+/// Splits `data` into the chunks of `encrypt_chunked` and passes the length
+/// prefix and ciphertext of each to `sink`, in order.
+fn encrypt_chunks<R, H, K, F>(rng: &mut R, pub_key: &K, data: &[u8], mut sink: F) -> Result<()>
+where
+    R: Rng,
+    H: Digest + DynDigest,
+    K: PublicKey,
+    F: FnMut(&[u8], &[u8]) -> Result<()>,
+{
+    let max_message_len = max_chunk_len::<H, _>(pub_key)?;
+    let mut digest = <H as Digest>::new();
+
+    let mut push = |chunk: &[u8]| -> Result<()> {
+        let ciphertext = encrypt(rng, pub_key, chunk, &mut digest, None)?;
+        sink(&(ciphertext.len() as u32).to_be_bytes(), &ciphertext)
+    };
+
+    if data.is_empty() {
+        push(&[])?;
+    }
+    for chunk in data.chunks(max_message_len) {
+        push(chunk)?;
+    }
+    Ok(())
+}
+
+/// Decrypts the output of `encrypt_chunked` and returns the concatenated
+/// plaintext. If an `rng` is passed, it uses RSA blinding for every chunk.
+///
+/// Every chunk must carry a length prefix equal to the key size. See
+/// `encrypt_chunked` for what this format does not protect against.
+pub fn decrypt_chunked<R: Rng, H: Digest + DynDigest, SK: PrivateKey>(
+    mut rng: Option<&mut R>,
+    priv_key: &SK,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    key::check_public(priv_key)?;
+
+    let k = priv_key.size();
+    let mut digest = <H as Digest>::new();
+    let mut out = Vec::new();
+    let mut rest = ciphertext;
+
+    if rest.is_empty() {
+        return Err(Error::Decryption);
+    }
+
+    while !rest.is_empty() {
+        if rest.len() < CHUNK_LEN_PREFIX {
+            return Err(Error::Decryption);
+        }
+        let (prefix, tail) = rest.split_at(CHUNK_LEN_PREFIX);
+        let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        if len != k || tail.len() < len {
+            return Err(Error::Decryption);
+        }
+        let (chunk, tail) = tail.split_at(len);
+
+        let msg = decrypt(rng.as_deref_mut(), priv_key, chunk, &mut digest, None)?;
+        out.extend_from_slice(&msg);
+        rest = tail;
+    }
+
+    Ok(out)
+}
+
+/// Returns how many bytes of the message go into each chunk.
+fn max_chunk_len<H: Digest, K: PublicKey>(pub_key: &K) -> Result<usize> {
+    let h_size = <H as Digest>::output_size();
+    match pub_key.size().checked_sub(2 * h_size + 2) {
+        Some(0) | None => Err(Error::MessageTooLong),
+        Some(len) => Ok(len),
+    }
+}
+
+/// Decrypts the chunks `encrypt_to_writer` or `encrypt_chunked` wrote to
+/// `reader`, until its end, and returns the concatenated plaintext. If an
+/// `rng` is passed, it uses RSA blinding for every chunk.
+///
+/// Only one chunk at a time is held in memory. A stream that ends inside a
+/// chunk fails with `Error::Decryption`, like any other malformed chunk,
+/// other failures of `reader` with `Error::ParseError`.
+#[cfg(feature = "std")]
+pub fn decrypt_from_reader<R: Rng, H: Digest + DynDigest, SK: PrivateKey, Rd: std::io::Read>(
+    mut rng: Option<&mut R>,
+    priv_key: &SK,
+    reader: &mut Rd,
+) -> Result<Vec<u8>> {
+    use std::io::ErrorKind;
+
+    key::check_public(priv_key)?;
+
+    let read_error = |err: std::io::Error| match err.kind() {
+        ErrorKind::UnexpectedEof => Error::Decryption,
+        _ => Error::ParseError {
+            reason: format!("failed to read ciphertext: {}", err),
+        },
+    };
+
+    let k = priv_key.size();
+    let mut digest = <H as Digest>::new();
+    let mut out = Vec::new();
+    let mut chunk = vec![0u8; k];
+    let mut chunks = 0;
+
+    loop {
+        // A clean end of the stream is only allowed before a length prefix.
+        let mut prefix = [0u8; CHUNK_LEN_PREFIX];
+        let mut filled = 0;
+        while filled < prefix.len() {
+            match reader.read(&mut prefix[filled..]) {
+                Ok(0) if filled == 0 && chunks > 0 => return Ok(out),
+                Ok(0) => return Err(Error::Decryption),
+                Ok(n) => filled += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(read_error(err)),
+            }
+        }
+        if u32::from_be_bytes(prefix) as usize != k {
+            return Err(Error::Decryption);
+        }
+
+        reader.read_exact(&mut chunk).map_err(read_error)?;
+        let msg = decrypt(rng.as_mut(), priv_key, &chunk, &mut digest, None)?;
+        out.extend_from_slice(&msg);
+        chunks += 1;
+    }
+}
+
 /// Decrypts a plaintext using RSA and the padding scheme from pkcs1# OAEP
 /// If an `rng` is passed, it uses RSA blinding to avoid timing side-channel attacks.
 ///
@@ -153,3 +347,104 @@ fn decrypt_inner<R: Rng, SK: PrivateKey>(
 
     Ok(CtOption::new((em, index + 2 + (h_size * 2) as u32), valid))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, PublicKeyParts, RSAPrivateKey};
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use sha1::Sha1;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_encrypt_decrypt_chunked() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024().clone();
+        let pub_key = priv_key.to_public_key();
+        let k = pub_key.size();
+        // 128 - 2 * 32 - 2
+        let max_message_len = 62;
+
+        for &len in &[0, 1, max_message_len, max_message_len + 1, 5000] {
+            let mut data = vec![0u8; len];
+            rng.fill(&mut data[..]);
+
+            let ciphertext = encrypt_chunked::<_, Sha256, _>(&mut rng, &pub_key, &data).unwrap();
+            let chunks = len.div_ceil(max_message_len).max(1);
+            assert_eq!(ciphertext.len(), chunks * (CHUNK_LEN_PREFIX + k));
+
+            let plaintext =
+                decrypt_chunked::<_, Sha256, _>(Some(&mut rng), &priv_key, &ciphertext).unwrap();
+            assert_eq!(plaintext, data);
+            let plaintext =
+                decrypt_chunked::<StdRng, Sha256, _>(None, &priv_key, &ciphertext).unwrap();
+            assert_eq!(plaintext, data);
+
+            assert!(decrypt_chunked::<StdRng, Sha1, _>(None, &priv_key, &ciphertext).is_err());
+            assert!(decrypt_chunked::<StdRng, Sha256, _>(
+                None,
+                &priv_key,
+                &ciphertext[..ciphertext.len() - 1]
+            )
+            .is_err());
+        }
+
+        assert!(decrypt_chunked::<StdRng, Sha256, _>(None, &priv_key, &[]).is_err());
+
+        let small = RSAPrivateKey::new(&mut rng, 512).unwrap();
+        // 64 - 2 * 32 - 2 leaves no room for a message.
+        match encrypt_chunked::<_, Sha256, _>(&mut rng, &small.to_public_key(), b"x") {
+            Err(Error::MessageTooLong) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_encrypt_to_writer_decrypt_from_reader() {
+        use std::io::Cursor;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+
+        for &len in &[0, 1, 62, 63, 5000] {
+            let mut data = vec![0u8; len];
+            rng.fill(&mut data[..]);
+
+            let mut written = Vec::new();
+            encrypt_to_writer::<_, Sha256, _, _>(&mut rng, &pub_key, &data, &mut written).unwrap();
+            let chunks = (len.max(1) - 1) / 62 + 1;
+            assert_eq!(written.len(), chunks * (CHUNK_LEN_PREFIX + 128));
+
+            // The stream can be read back, and so can `encrypt_chunked` output.
+            let mut reader = Cursor::new(&written);
+            let plaintext =
+                decrypt_from_reader::<_, Sha256, _, _>(Some(&mut rng), priv_key, &mut reader)
+                    .unwrap();
+            assert_eq!(plaintext, data);
+            assert_eq!(reader.position() as usize, written.len());
+
+            let chunked = encrypt_chunked::<_, Sha256, _>(&mut rng, &pub_key, &data).unwrap();
+            let mut reader = Cursor::new(chunked);
+            let plaintext =
+                decrypt_from_reader::<StdRng, Sha256, _, _>(None, priv_key, &mut reader).unwrap();
+            assert_eq!(plaintext, data);
+
+            // Streams cut inside a chunk or its prefix are rejected.
+            for &cut in &[1, CHUNK_LEN_PREFIX + 1, written.len() - 1] {
+                let mut reader = Cursor::new(&written[..cut]);
+                match decrypt_from_reader::<StdRng, Sha256, _, _>(None, priv_key, &mut reader) {
+                    Err(Error::Decryption) => {}
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+        }
+
+        let mut empty = Cursor::new(Vec::new());
+        match decrypt_from_reader::<StdRng, Sha256, _, _>(None, priv_key, &mut empty) {
+            Err(Error::Decryption) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
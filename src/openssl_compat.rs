@@ -0,0 +1,191 @@
+//! Conversions to and from the key types of the `openssl` crate, enabled with
+//! the `openssl` feature.
+//!
+//! The components are copied directly, without a round trip through DER.
+//! OpenSSL keys built here carry the CRT values of the private key, keys
+//! taken from OpenSSL have theirs checked against the private exponent and
+//! primes. Multi-prime keys cannot be converted, the `openssl` crate only
+//! builds keys from two primes.
+
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use num_bigint::BigUint;
+use openssl::bn::{BigNum, BigNumRef};
+use openssl::error::ErrorStack;
+use openssl::pkey::{Private, Public};
+use openssl::rsa::Rsa;
+use zeroize::Zeroize;
+
+use crate::errors::{Error, Result};
+use crate::key::{PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+
+fn to_bignum(value: &BigUint) -> Result<BigNum> {
+    let mut bytes = value.to_bytes_be();
+    let bn = BigNum::from_slice(&bytes).map_err(encode_error);
+    bytes.zeroize();
+    bn
+}
+
+fn from_bignum(value: &BigNumRef) -> BigUint {
+    let mut bytes = value.to_vec();
+    let n = BigUint::from_bytes_be(&bytes);
+    bytes.zeroize();
+    n
+}
+
+fn encode_error(err: ErrorStack) -> Error {
+    Error::EncodeError {
+        reason: err.to_string(),
+    }
+}
+
+fn parse_error(reason: &str) -> Error {
+    Error::ParseError {
+        reason: reason.to_string(),
+    }
+}
+
+impl TryFrom<&RSAPublicKey> for Rsa<Public> {
+    type Error = Error;
+
+    fn try_from(key: &RSAPublicKey) -> Result<Self> {
+        Rsa::from_public_components(to_bignum(key.n())?, to_bignum(key.e())?).map_err(encode_error)
+    }
+}
+
+impl TryFrom<&Rsa<Public>> for RSAPublicKey {
+    type Error = Error;
+
+    fn try_from(key: &Rsa<Public>) -> Result<Self> {
+        RSAPublicKey::new(from_bignum(key.n()), from_bignum(key.e()))
+    }
+}
+
+impl TryFrom<&RSAPrivateKey> for Rsa<Private> {
+    type Error = Error;
+
+    fn try_from(key: &RSAPrivateKey) -> Result<Self> {
+        if key.primes().len() != 2 {
+            return Err(Error::EncodeError {
+                reason: "OpenSSL keys have exactly two primes".to_string(),
+            });
+        }
+        let precomputed = key.precomputed_values().ok_or(Error::InvalidPrime)?;
+        let qinv = precomputed
+            .qinv
+            .to_biguint()
+            .ok_or(Error::InvalidCoefficient)?;
+
+        Rsa::from_private_components(
+            to_bignum(key.n())?,
+            to_bignum(key.e())?,
+            to_bignum(key.d())?,
+            to_bignum(&key.primes()[0])?,
+            to_bignum(&key.primes()[1])?,
+            to_bignum(&precomputed.dp)?,
+            to_bignum(&precomputed.dq)?,
+            to_bignum(&qinv)?,
+        )
+        .map_err(encode_error)
+    }
+}
+
+impl TryFrom<&Rsa<Private>> for RSAPrivateKey {
+    type Error = Error;
+
+    /// Fails for keys without their primes, and for keys whose CRT values do
+    /// not match the private exponent.
+    fn try_from(key: &Rsa<Private>) -> Result<Self> {
+        let (p, q) = match (key.p(), key.q()) {
+            (Some(p), Some(q)) => (from_bignum(p), from_bignum(q)),
+            _ => return Err(parse_error("the OpenSSL key has no primes")),
+        };
+        let converted = RSAPrivateKey::from_components(
+            from_bignum(key.n()),
+            from_bignum(key.e()),
+            from_bignum(key.d()),
+            vec![p, q],
+        );
+        converted.validate()?;
+
+        if let (Some(dp), Some(dq), Some(qinv)) = (key.dmp1(), key.dmq1(), key.iqmp()) {
+            let precomputed = converted.precomputed_values().ok_or(Error::InvalidPrime)?;
+            let expected: Vec<BigUint> = vec![
+                precomputed.dp.clone(),
+                precomputed.dq.clone(),
+                precomputed
+                    .qinv
+                    .to_biguint()
+                    .ok_or(Error::InvalidCoefficient)?,
+            ];
+            let mut stored = vec![from_bignum(dp), from_bignum(dq), from_bignum(qinv)];
+            let matches = expected == stored;
+            stored.iter_mut().for_each(Zeroize::zeroize);
+            if !matches {
+                return Err(parse_error("CRT values do not match the private key"));
+            }
+        }
+        Ok(converted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::generate_multi_prime_key;
+    use crate::test_util;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_round_trip() {
+        let key = test_util::test_key_2048();
+
+        let ossl = Rsa::<Private>::try_from(key).unwrap();
+        assert!(ossl.check_key().unwrap());
+        assert_eq!(&RSAPrivateKey::try_from(&ossl).unwrap(), key);
+
+        let public = key.to_public_key();
+        let ossl = Rsa::<Public>::try_from(&public).unwrap();
+        assert_eq!(RSAPublicKey::try_from(&ossl).unwrap(), public);
+    }
+
+    #[test]
+    fn test_from_openssl_generated() {
+        let ossl = Rsa::generate(1024).unwrap();
+        let key = RSAPrivateKey::try_from(&ossl).unwrap();
+        assert_eq!(key.n().to_bytes_be(), ossl.n().to_vec());
+        assert_eq!(key.primes().len(), 2);
+
+        // A mismatched coefficient is rejected.
+        let bad_iqmp = BigNum::from_u32(1).unwrap();
+        let bad = Rsa::from_private_components(
+            ossl.n().to_owned().unwrap(),
+            ossl.e().to_owned().unwrap(),
+            ossl.d().to_owned().unwrap(),
+            ossl.p().unwrap().to_owned().unwrap(),
+            ossl.q().unwrap().to_owned().unwrap(),
+            ossl.dmp1().unwrap().to_owned().unwrap(),
+            ossl.dmq1().unwrap().to_owned().unwrap(),
+            bad_iqmp,
+        )
+        .unwrap();
+        match RSAPrivateKey::try_from(&bad) {
+            Err(Error::ParseError { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_multi_prime_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let key = generate_multi_prime_key(&mut rng, 3, 512).unwrap();
+        match Rsa::<Private>::try_from(&key) {
+            Err(Error::EncodeError { .. }) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
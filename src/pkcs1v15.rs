@@ -1,11 +1,30 @@
+use digest::Digest;
+#[cfg(feature = "implicit-rejection")]
+use hmac::{Hmac, Mac, NewMac};
+use num_bigint::BigUint;
 use rand::Rng;
+#[cfg(feature = "implicit-rejection")]
+use sha2::Sha256;
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+#[cfg(feature = "implicit-rejection")]
+use zeroize::Zeroize;
 use alloc::vec;
 use alloc::vec::Vec;
 
 use crate::errors::{Error, Result};
-use crate::hash::Hash;
-use crate::key::{self, PrivateKey, PublicKey};
+use crate::hash::{new_digest, Hash};
+#[cfg(feature = "implicit-rejection")]
+use crate::internals::left_pad;
+#[cfg(feature = "implicit-rejection")]
+use crate::key::PublicKeyParts;
+use crate::key::{self, PrivateKey, PublicKey, RSAPrivateKey, RSAPublicKey};
+
+#[cfg(feature = "implicit-rejection")]
+type HmacSha256 = Hmac<Sha256>;
+
+/// Number of candidates drawn for the length of a synthetic message.
+#[cfg(feature = "implicit-rejection")]
+const SYNTHETIC_LENGTH_CANDIDATES: usize = 128;
 
 // Encrypts the given message with RSA and the padding
 // scheme from PKCS#1 v1.5.  The message must be no longer than the
@@ -31,20 +50,40 @@ pub fn encrypt<R: Rng, PK: PublicKey>(rng: &mut R, pub_key: &PK, msg: &[u8]) ->
 
 /// Decrypts a plaintext using RSA and the padding scheme from PKCS#1 v1.5.
 // If an `rng` is passed, it uses RSA blinding to avoid timing side-channel attacks.
-//
-// Note that whether this function returns an error or not discloses secret
-// information. If an attacker can cause this function to run repeatedly and
-// learn whether each instance returned an error then they can decrypt and
-// forge signatures as if they had the private key. See
-// `decrypt_session_key` for a way of solving this problem.
+///
+/// With the `implicit-rejection` feature, which is on by default, invalid
+/// padding is not reported. Such a ciphertext decrypts to a synthetic message
+/// derived from the ciphertext and the private exponent instead, following
+/// the implicit rejection algorithm of draft-irtf-cfrg-rsa-guidance (as used
+/// by OpenSSL 3.2 and later). The same ciphertext always yields the same
+/// message, so the result does not reveal whether the padding was correct;
+/// the protocol using it must then fail on its own, e.g. when a synthetic
+/// session key does not decrypt the payload. Errors are only returned for
+/// publicly invalid input: a ciphertext longer than the modulus, or one which
+/// does not represent a number below it.
+///
+/// Without the feature, invalid padding fails with `Error::Decryption`. Note
+/// that whether this function returns an error or not then discloses secret
+/// information. If an attacker can cause this function to run repeatedly and
+/// learn whether each instance returned an error then they can decrypt and
+/// forge signatures as if they had the private key.
 #[inline]
-pub fn decrypt<R: Rng, SK: PrivateKey>(
+pub fn decrypt<R: Rng>(
     rng: Option<&mut R>,
-    priv_key: &SK,
+    priv_key: &RSAPrivateKey,
     ciphertext: &[u8],
 ) -> Result<Vec<u8>> {
     key::check_public(priv_key)?;
 
+    decrypt_and_unpad(rng, priv_key, ciphertext)
+}
+
+#[cfg(not(feature = "implicit-rejection"))]
+fn decrypt_and_unpad<R: Rng>(
+    rng: Option<&mut R>,
+    priv_key: &RSAPrivateKey,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
     let (valid, out, index) = decrypt_inner(rng, priv_key, ciphertext)?;
     if valid == 0 {
         return Err(Error::Decryption);
@@ -53,6 +92,98 @@ pub fn decrypt<R: Rng, SK: PrivateKey>(
     Ok(out[index as usize..].to_vec())
 }
 
+#[cfg(feature = "implicit-rejection")]
+fn decrypt_and_unpad<R: Rng>(
+    rng: Option<&mut R>,
+    priv_key: &RSAPrivateKey,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    let k = priv_key.size();
+    // The PRF encodes its output length in bits as a u16.
+    if ciphertext.len() > k || k * 8 > u16::MAX as usize {
+        return Err(Error::Decryption);
+    }
+
+    let (valid, mut em, index) = decrypt_inner(rng, priv_key, ciphertext)?;
+
+    let mut kdk = derive_kdk(priv_key, ciphertext);
+    let mut synthetic = vec![0u8; k];
+    prf(&kdk, b"message", &mut synthetic);
+    let synthetic_length = synthetic_length(&kdk, k);
+    kdk.zeroize();
+
+    let valid = Choice::from(valid);
+    let index = u32::conditional_select(&((k - synthetic_length) as u32), &index, valid);
+
+    // Read both buffers in full so memory accesses do not depend on `valid`.
+    let out = (index as usize..k)
+        .map(|i| u8::conditional_select(&synthetic[i], &em[i], valid))
+        .collect();
+
+    em.zeroize();
+    synthetic.zeroize();
+
+    Ok(out)
+}
+
+/// Derives the key derivation key used for implicit rejection:
+/// `HMAC-SHA256(SHA256(d), C)`, with `d` and `C` padded to the modulus size.
+#[cfg(feature = "implicit-rejection")]
+fn derive_kdk(priv_key: &RSAPrivateKey, ciphertext: &[u8]) -> Vec<u8> {
+    let k = priv_key.size();
+
+    let mut d = left_pad(&priv_key.d().to_bytes_be(), k);
+    let mut d_hash = Sha256::digest(&d);
+    d.zeroize();
+
+    let mut mac = HmacSha256::new_varkey(&d_hash).expect("HMAC accepts keys of any size");
+    d_hash[..].zeroize();
+    mac.update(&left_pad(ciphertext, k));
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Fills `out` with the output of the implicit rejection PRF for `label`.
+#[cfg(feature = "implicit-rejection")]
+fn prf(kdk: &[u8], label: &[u8], out: &mut [u8]) {
+    let bit_len = (out.len() * 8) as u16;
+
+    for (i, chunk) in out.chunks_mut(32).enumerate() {
+        let mut mac = HmacSha256::new_varkey(kdk).expect("HMAC accepts keys of any size");
+        mac.update(&(i as u16).to_be_bytes());
+        mac.update(label);
+        mac.update(&bit_len.to_be_bytes());
+
+        chunk.copy_from_slice(&mac.finalize().into_bytes()[..chunk.len()]);
+    }
+}
+
+/// Picks the length of the synthetic message for a `k` byte modulus: the last
+/// of the PRF drawn candidates that is below the maximum message length, or
+/// zero if there is none.
+#[cfg(feature = "implicit-rejection")]
+fn synthetic_length(kdk: &[u8], k: usize) -> usize {
+    let mut candidates = [0u8; 2 * SYNTHETIC_LENGTH_CANDIDATES];
+    prf(kdk, b"length", &mut candidates);
+
+    // PS is at least 8 bytes, preceded by 0x00 0x02.
+    let max_len = (k - 2 - 8) as u16;
+    let mut mask = max_len;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+
+    let mut length = 0u16;
+    for candidate in candidates.chunks(2) {
+        let candidate = u16::from_be_bytes([candidate[0], candidate[1]]) & mask;
+        let fits = ((u32::from(candidate).wrapping_sub(u32::from(max_len)) >> 31) & 1) as u8;
+        length.conditional_assign(&candidate, Choice::from(fits));
+    }
+
+    length as usize
+}
+
 // Calculates the signature of hashed using
 // RSASSA-PKCS1-V1_5-SIGN from RSA PKCS#1 v1.5. Note that `hashed` must
 // be the result of hashing the input message using the given hash
@@ -93,6 +224,9 @@ pub fn sign<R: Rng, SK: PrivateKey>(
 }
 
 /// Verifies an RSA PKCS#1 v1.5 signature.
+///
+/// `sig` must be exactly as long as the modulus and smaller than it, see
+/// `key::validate_representative`, anything else is `Error::Verification`.
 #[inline]
 pub fn verify<PK: PublicKey>(
     pub_key: &PK,
@@ -108,6 +242,7 @@ pub fn verify<PK: PublicKey>(
         return Err(Error::Verification);
     }
 
+    key::validate_representative(pub_key, sig).map_err(|_| Error::Verification)?;
     let em = pub_key.raw_encryption_primitive(sig, pub_key.size())?;
 
     // EM = 0x00 || 0x01 || PS || 0x00 || T
@@ -128,13 +263,172 @@ pub fn verify<PK: PublicKey>(
     Ok(())
 }
 
+/// Signs `hash` with RSASSA-PKCS1-v1_5 without a `DigestInfo`, padding the
+/// bytes directly as the data block, the `NONEwithRSA` mode of some older
+/// HSMs and smart cards. Same as [`sign`] with `hash` set to `None`.
+///
+/// Only use this to interoperate with such devices. Without the `DigestInfo`
+/// the signature does not say which hash was used, so the verifier has to
+/// know it out of band, and two hash functions with outputs of the same
+/// length cannot be told apart. Nothing here checks that `hash` is a hash
+/// at all: any data up to `k - 11` bytes is signed as given, so never pass
+/// bytes an attacker chose or a message that was not hashed.
+pub fn sign_raw_hash<R: Rng, SK: PrivateKey>(
+    rng: Option<&mut R>,
+    priv_key: &SK,
+    hash: &[u8],
+) -> Result<Vec<u8>> {
+    sign(rng, priv_key, None, hash)
+}
+
+/// Verifies a signature made by [`sign_raw_hash`], or by a device in the
+/// `NONEwithRSA` mode, of the bare hash `hash`. Same as [`verify`] with
+/// `hash` set to `None`, with the same caveats as [`sign_raw_hash`].
+pub fn verify_raw_hash<PK: PublicKey>(pub_key: &PK, hash: &[u8], sig: &[u8]) -> Result<()> {
+    verify(pub_key, None, hash, sig)
+}
+
+/// Verifies the signature of an X.509 certificate made with
+/// `sha*WithRSAEncryption` by the issuer holding `issuer_pub`.
+///
+/// `tbs_der` is the DER encoded `TBSCertificate`, exactly as it appears in the
+/// certificate, and `sig` the contents of its `signatureValue` bit string.
+/// `H` hashes the TBS bytes and `hash` names the same function for the
+/// `DigestInfo` prefix, matching the certificate's `signatureAlgorithm`.
+///
+/// Only the signature is checked. Parsing the certificate, matching it to its
+/// issuer and checking validity periods and extensions is up to the caller.
+pub fn verify_cert_signature<H: Digest, PK: PublicKey>(
+    issuer_pub: &PK,
+    hash: Hash,
+    tbs_der: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    let hashed = H::digest(tbs_der);
+    verify(issuer_pub, Some(&hash), &hashed, sig)
+}
+
+/// Hashes `msg` with `H` and verifies the RSA PKCS#1 v1.5 signature `sig`
+/// of it, with the `DigestInfo` prefix of `hash`.
+///
+/// `hash` has to name `H`, an `H` with another output length fails with
+/// `Error::DigestLength`. A bad signature of any kind fails with
+/// `Error::Verification`, as with [`verify`].
+pub fn verify_message<H: Digest, PK: PublicKey>(
+    pub_key: &PK,
+    hash: Hash,
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    let hashed = H::digest(msg);
+    if hashed.len() != hash.size() {
+        return Err(Error::DigestLength {
+            expected: hash.size(),
+            got: hashed.len(),
+        });
+    }
+    verify(pub_key, Some(&hash), &hashed, sig).map_err(|_| Error::Verification)
+}
+
+/// Verifies an RSA PKCS#1 v1.5 signature of `msg`, accepting only a
+/// `DigestInfo` that names one of the hashes in `allowed`.
+///
+/// The hash is read from the signature, and `msg` is hashed with it, so a
+/// verifier that accepts several hashes does not need to know in advance
+/// which one the signer used. A signature made with any other hash fails with
+/// `Error::Verification`, even if it is otherwise valid, so that a signer
+/// cannot be downgraded to a weaker hash than the policy allows.
+///
+/// `allowed` may only name the SHA-2 hashes, which are the ones this crate
+/// can compute, anything else fails with `Error::InvalidPaddingScheme`.
+pub fn verify_with_allowed_hashes<PK: PublicKey>(
+    pub_key: &PK,
+    msg: &[u8],
+    sig: &[u8],
+    allowed: &[Hash],
+) -> Result<()> {
+    let mut digests = Vec::with_capacity(allowed.len());
+    for hash in allowed {
+        digests.push((*hash, new_digest(*hash)?));
+    }
+
+    let k = pub_key.size();
+    if sig.len() != k {
+        return Err(Error::Verification);
+    }
+    let em = pub_key.raw_encryption_primitive(sig, k)?;
+
+    // EM = 0x00 || 0x01 || PS || 0x00 || T, with at least 8 bytes of PS.
+    if em[0] != 0 || em[1] != 1 {
+        return Err(Error::Verification);
+    }
+    let ps_len = em[2..].iter().take_while(|&&b| b == 0xff).count();
+    if ps_len < 8 || em.get(2 + ps_len) != Some(&0) {
+        return Err(Error::Verification);
+    }
+    let t = &em[3 + ps_len..];
+
+    // The prefixes encode the length of the digest, so at most one matches.
+    let (hash, digest) = digests
+        .iter_mut()
+        .find(|(hash, _)| {
+            let prefix = hash.asn1_prefix();
+            t.len() == prefix.len() + hash.size() && t.starts_with(prefix)
+        })
+        .ok_or(Error::Verification)?;
+
+    digest.update(msg);
+    let hashed = digest.finalize_reset();
+    verify(pub_key, Some(hash), &hashed, sig)
+}
+
+/// Returns whether every `(hashed, sig)` pair of `pairs` is a PKCS#1 v1.5
+/// signature under the modulus `n` and public exponent `e`, with the
+/// `DigestInfo` of `hash` as in [`verify`].
+///
+/// **This is a forensic tool for reverse engineering, not a way to verify
+/// signatures.** It helps finding out which key signed a set of known
+/// messages, e.g. when a device ships without documenting its key. `e` is
+/// not checked against the limits `RSAPublicKey::new` imposes, and an empty
+/// `pairs` matches nothing. Use [`verify`] to decide whether a signature is
+/// valid.
+pub fn signatures_match_modulus(
+    n: &BigUint,
+    e: &BigUint,
+    hash: Option<&Hash>,
+    pairs: &[(&[u8], &[u8])],
+) -> bool {
+    let candidate = RSAPublicKey::new_unchecked(n.clone(), e.clone());
+    !pairs.is_empty()
+        && pairs
+            .iter()
+            .all(|(hashed, sig)| verify(&candidate, hash, hashed, sig).is_ok())
+}
+
+/// Returns the first of `candidates` that matches all of `pairs` with the
+/// exponent `e` according to [`signatures_match_modulus`]. Like that, this
+/// is a forensic tool and not a way to verify signatures.
+pub fn find_signing_modulus<'a>(
+    candidates: &'a [BigUint],
+    e: &BigUint,
+    hash: Option<&Hash>,
+    pairs: &[(&[u8], &[u8])],
+) -> Option<&'a BigUint> {
+    candidates
+        .iter()
+        .find(|n| signatures_match_modulus(n, e, hash, pairs))
+}
+
 #[inline]
 fn hash_info(hash: Option<&Hash>, digest_len: usize) -> Result<(usize, &'static [u8])> {
     match hash {
         Some(hash) => {
             let hash_len = hash.size();
             if digest_len != hash_len {
-                return Err(Error::InputNotHashed);
+                return Err(Error::DigestLength {
+                    expected: hash_len,
+                    got: digest_len,
+                });
             }
 
             Ok((hash_len, hash.asn1_prefix()))
@@ -217,11 +511,60 @@ mod tests {
     use num_bigint::BigUint;
     use num_traits::FromPrimitive;
     use num_traits::Num;
+    use num_bigint::RandBigInt;
     use rand::{SeedableRng, rngs::StdRng};
-    use sha1::{Digest, Sha1};
+    use sha1::Sha1;
     use std::time::SystemTime;
 
-    use crate::{Hash, PaddingScheme, PublicKey, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+    use crate::{
+        test_util, Hash, PaddingScheme, PublicKey, PublicKeyParts, RSAPrivateKey, RSAPublicKey,
+    };
+
+    #[test]
+    fn test_find_signing_modulus() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let keys: Vec<RSAPrivateKey> = (0..3)
+            .map(|_| RSAPrivateKey::new(&mut rng, 512).unwrap())
+            .collect();
+        let moduli: Vec<BigUint> = keys.iter().map(|key| key.n().clone()).collect();
+        let e = &BigUint::from_u64(65537).unwrap();
+        let hash = Some(&Hash::SHA1);
+
+        let digests = [Sha1::digest(b"first"), Sha1::digest(b"second")];
+        let sigs: Vec<Vec<u8>> = digests
+            .iter()
+            .map(|d| sign::<StdRng, _>(None, &keys[1], hash, d).unwrap())
+            .collect();
+        let pairs = [
+            (&digests[0][..], &sigs[0][..]),
+            (&digests[1][..], &sigs[1][..]),
+        ];
+
+        assert_eq!(
+            find_signing_modulus(&moduli, e, hash, &pairs),
+            Some(&moduli[1])
+        );
+        assert!(signatures_match_modulus(&moduli[1], e, hash, &pairs[..1]));
+        assert!(!signatures_match_modulus(&moduli[0], e, hash, &pairs));
+        assert!(!signatures_match_modulus(
+            &moduli[1],
+            &BigUint::from_u64(3).unwrap(),
+            hash,
+            &pairs
+        ));
+        assert!(!signatures_match_modulus(
+            &moduli[1],
+            e,
+            Some(&Hash::SHA2_256),
+            &pairs
+        ));
+        assert!(!signatures_match_modulus(&moduli[1], e, hash, &[]));
+
+        // One pair from another key rules the candidate out.
+        let other = sign::<StdRng, _>(None, &keys[2], hash, &digests[0]).unwrap();
+        let mixed = [pairs[0], (&digests[0][..], &other[..])];
+        assert_eq!(find_signing_modulus(&moduli, e, hash, &mixed), None);
+    }
 
     #[test]
     fn test_non_zero_bytes() {
@@ -311,6 +654,68 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "implicit-rejection")]
+    fn test_decrypt() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let k = priv_key.size();
+
+        for i in 0..k - 10 {
+            let input: Vec<u8> = (0..i).map(|_| rng.gen()).collect();
+            let ciphertext = encrypt(&mut rng, &pub_key, &input).unwrap();
+            let plaintext = decrypt(Some(&mut rng), &priv_key, &ciphertext).unwrap();
+            assert_eq!(input, plaintext);
+        }
+
+        for _ in 0..32 {
+            let ciphertext = left_pad(&rng.gen_biguint_below(priv_key.n()).to_bytes_be(), k);
+            let (valid, _, _) = decrypt_inner::<StdRng, _>(None, &priv_key, &ciphertext).unwrap();
+            if valid == 1 {
+                continue;
+            }
+
+            let synthetic = decrypt::<StdRng>(None, &priv_key, &ciphertext).unwrap();
+            assert!(synthetic.len() < k - 10);
+
+            let blinded = decrypt(Some(&mut rng), &priv_key, &ciphertext).unwrap();
+            assert_eq!(synthetic, blinded);
+
+            let unpadded = BigUint::from_bytes_be(&ciphertext).to_bytes_be();
+            let unpadded = decrypt::<StdRng>(None, &priv_key, &unpadded).unwrap();
+            assert_eq!(synthetic, unpadded);
+        }
+
+        // Ciphertexts of k repeated bytes, decrypted by OpenSSL 3.3 (which rejects
+        // implicitly by default) with
+        // `openssl pkeyutl -decrypt -inkey key.pem -pkeyopt rsa_padding_mode:pkcs1`.
+        let vectors = [
+            (
+                0x42u8,
+                "9d49b2f17714ce03634867788256c94a33821cf8c4d7286d3f52b88851314c16d2",
+            ),
+            (
+                0x01,
+                "457e49ac70d43460751762fb86f0a2037c6f0e2bfab96137039fbcc784dd",
+            ),
+            (0x7f, "311ddfcdead0b9ffc9604956c34e1cc5dc23"),
+        ];
+        for (byte, expected) in &vectors {
+            let ciphertext = vec![*byte; k];
+            let (valid, _, _) = decrypt_inner::<StdRng, _>(None, &priv_key, &ciphertext).unwrap();
+            assert_eq!(valid, 0);
+            let synthetic = decrypt::<StdRng>(None, &priv_key, &ciphertext).unwrap();
+            assert_eq!(hex::encode(synthetic), *expected);
+        }
+
+        // Publicly invalid ciphertexts are still rejected.
+        let too_long = vec![0u8; k + 1];
+        assert!(decrypt::<StdRng>(None, &priv_key, &too_long).is_err());
+        let too_large = priv_key.n().to_bytes_be();
+        assert!(decrypt::<StdRng>(None, &priv_key, &too_large).is_err());
+    }
+
     #[test]
     fn test_sign_pkcs1v15() {
         let priv_key = get_private_key();
@@ -365,6 +770,127 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_rejects_bad_representative() {
+        let priv_key = test_util::test_key_2048();
+        let pub_key = priv_key.to_public_key();
+        let digest = Sha256::digest(b"order=1234&amount=9.99").to_vec();
+        let sig = sign::<StdRng, _>(None, &priv_key, Some(&Hash::SHA2_256), &digest).unwrap();
+        test_util::assert_rejects_malformed_signatures(&pub_key, &sig, |sig| {
+            verify(&pub_key, Some(&Hash::SHA2_256), &digest, sig)
+        });
+    }
+
+    #[test]
+    fn test_verify_openssl_sign_sha1() {
+        // PHP's `openssl_sign($data, $sig, $key)` defaults to
+        // OPENSSL_ALGO_SHA1, which signs through OpenSSL's EVP_Sign* just like
+        // `printf 'order=1234&amount=9.99' | openssl dgst -sha1 -sign key.pem`
+        // with the PEM form of `test_util::test_key_2048`.
+        let sig = hex::decode(concat!(
+            "2af405224b872f1de5b1fe54adb8aca2f3a95068634125898d56f5be6a277217",
+            "e88bd71af9d36a47c69000177120d4b3ca8e946379e0e138791935ff9d60a9a9",
+            "e9d85530bef5b54c04128c7bc380961ede6f021bd0e8de89d72b1ca832c0653b",
+            "5955d74e620a7624f9cc4c6a455236874538b57cc7c4e5ac8d284358567c8bea",
+            "1a5afe56c760628a9bfcd0f72acaa829792c9373036c893bf0efc36eacdeeaa2",
+            "3d7d5e79d66f1b63234b60cbc5c15bf47f87584af1b5cef0f738e868a3fb0d4f",
+            "bb9b9fbda7356e42f0263f17ae4d4b60cfc8750cd2a5f40a74e7e361cadbaa7e",
+            "c1543c752e6e6de5f0b4e22504eea15084c70ffed618cd7bc52ffb7ee0aaf3f0",
+        ))
+        .unwrap();
+        let priv_key = test_util::test_key_2048();
+        let pub_key = priv_key.to_public_key();
+        let digest = Sha1::digest(b"order=1234&amount=9.99").to_vec();
+
+        pub_key
+            .verify(
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA1)),
+                &digest,
+                &sig,
+            )
+            .expect("failed to verify");
+        // The padding is deterministic, so the same DigestInfo prefix gives
+        // the same signature.
+        let ours = priv_key
+            .sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA1)), &digest)
+            .unwrap();
+        assert_eq!(ours, sig);
+
+        // Without the prefix or with another hash the encoding differs.
+        assert!(pub_key
+            .verify(PaddingScheme::new_pkcs1v15_sign(None), &digest, &sig)
+            .is_err());
+        let mut padded = vec![0; 32];
+        padded[..digest.len()].copy_from_slice(&digest);
+        assert!(pub_key
+            .verify(
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                &padded,
+                &sig
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_with_allowed_hashes() {
+        let priv_key = test_util::test_key_2048();
+        let pub_key = priv_key.to_public_key();
+        let msg = b"order=1234&amount=9.99";
+
+        // A valid SHA-1 signature is rejected when only SHA-256 is allowed.
+        let digest = Sha1::digest(msg).to_vec();
+        let sha1_sig = priv_key
+            .sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA1)), &digest)
+            .unwrap();
+        match verify_with_allowed_hashes(&pub_key, msg, &sha1_sig, &[Hash::SHA2_256]) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let digest = Sha256::digest(msg).to_vec();
+        let sig = priv_key
+            .sign(
+                PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                &digest,
+            )
+            .unwrap();
+        let allowed = [Hash::SHA2_384, Hash::SHA2_256];
+        verify_with_allowed_hashes(&pub_key, msg, &sig, &allowed).expect("failed to verify");
+        verify_with_allowed_hashes(&pub_key, msg, &sig, &allowed[1..]).expect("failed to verify");
+        assert!(verify_with_allowed_hashes(&pub_key, msg, &sig, &allowed[..1]).is_err());
+        let other = b"order=1234&amount=0.99";
+        assert!(verify_with_allowed_hashes(&pub_key, other, &sig, &allowed).is_err());
+
+        // Hashes the crate cannot compute cannot be allowed.
+        match verify_with_allowed_hashes(&pub_key, msg, &sha1_sig, &[Hash::SHA1]) {
+            Err(Error::InvalidPaddingScheme) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_unreduced_signature() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let padding = || PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA1));
+
+        let mut fits_modulus_len = false;
+        for i in 0..32u8 {
+            let digest = Sha1::digest(&[i]).to_vec();
+            let sig = priv_key.sign(padding(), &digest).unwrap();
+            pub_key.verify(padding(), &digest, &sig).unwrap();
+
+            // s + n is congruent to s, but out of range.
+            let forged = (BigUint::from_bytes_be(&sig) + pub_key.n()).to_bytes_be();
+            fits_modulus_len |= forged.len() == pub_key.size();
+            match pub_key.verify(padding(), &digest, &forged) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        assert!(fits_modulus_len);
+    }
+
     #[test]
     fn test_unpadded_signature() {
         let msg = b"Thu Dec 19 18:06:16 EST 2013\n";
@@ -381,4 +907,123 @@ mod tests {
             .verify(PaddingScheme::new_pkcs1v15_sign(None), msg, &sig)
             .expect("failed to verify");
     }
+
+    // The DigiCert Global Root G2 certificate, self-signed with
+    // sha256WithRSAEncryption, and its public key.
+    const DIGICERT_ROOT_G2: &str = "\
+        MIIDjjCCAnagAwIBAgIQAzrx5qcRqaC7KGSxHQn65TANBgkqhkiG9w0BAQsFADBh\
+        MQswCQYDVQQGEwJVUzEVMBMGA1UEChMMRGlnaUNlcnQgSW5jMRkwFwYDVQQLExB3\
+        d3cuZGlnaWNlcnQuY29tMSAwHgYDVQQDExdEaWdpQ2VydCBHbG9iYWwgUm9vdCBH\
+        MjAeFw0xMzA4MDExMjAwMDBaFw0zODAxMTUxMjAwMDBaMGExCzAJBgNVBAYTAlVT\
+        MRUwEwYDVQQKEwxEaWdpQ2VydCBJbmMxGTAXBgNVBAsTEHd3dy5kaWdpY2VydC5j\
+        b20xIDAeBgNVBAMTF0RpZ2lDZXJ0IEdsb2JhbCBSb290IEcyMIIBIjANBgkqhkiG\
+        9w0BAQEFAAOCAQ8AMIIBCgKCAQEAuzfNNNx7a8myaJCtSnX/RrohCgiN9RlUyfuI\
+        2/Ou8jqJkTx65qsGGmvPrC3oXgkkRLpimn7Wo6h+4FR1IAWsULecYxpsMNzaHxmx\
+        1x7e/dfgy5SDN67sH0NO3Xss0r0upS/kqbitOtSZpLYl6ZtrAGCSYP9PIUkY92eQ\
+        q2EGnI/yuum06ZIya7XzV+hdG82MHauVBJVJ8zUtluNJbd134/tJS7SsVQepj5Wz\
+        tCO7TG1F8PapspUwtP1MVYwnSlcUfIKdzXOS0xZKBgyMUNGPHgm+F6HmIcr9g+UQ\
+        vIOlCsRnKPZzFBQ9RnbDhxSJITRNrw9FDKZJobq7nMWxM4MphQIDAQABo0IwQDAP\
+        BgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBhjAdBgNVHQ4EFgQUTiJUIBiV\
+        5uNu5g/6+rkS7QYXjzkwDQYJKoZIhvcNAQELBQADggEBAGBnKJRvDkhj6zHd6mcY\
+        1Yl9PMWLSn/pvtsrF9+wX3N3KjITOYFnQoQj8kVnNeyIv/iPsGEMNKSuIEyExtv4\
+        NeF22d+mQrvHRAiGfzZ0JFrabA0UWTW98kndth/Jsw1HKj2ZL7tcu7XUIOGZX1NG\
+        Fdtom/DzMNU+MeKNhJ7jitralj41E6Vf8PlwUHBHQRFXGU7Aj64GxJUTFy8bJZ91\
+        8rGOmaFvE7FBcf6IKshPECBV1/MUReXgRPTqh5Uykw7+U0b6LJ3/iyK5S9kJRaTe\
+        pLiaWN0bfVKfjllDiIGknibVb63dDcY3fe0Dkhvld1927jyNxF1WW6LZZm6zNTfl\
+        MrY=";
+    const DIGICERT_ROOT_G2_PUBLIC_KEY: &str = "\
+        MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAuzfNNNx7a8myaJCtSnX/\
+        RrohCgiN9RlUyfuI2/Ou8jqJkTx65qsGGmvPrC3oXgkkRLpimn7Wo6h+4FR1IAWs\
+        ULecYxpsMNzaHxmx1x7e/dfgy5SDN67sH0NO3Xss0r0upS/kqbitOtSZpLYl6Ztr\
+        AGCSYP9PIUkY92eQq2EGnI/yuum06ZIya7XzV+hdG82MHauVBJVJ8zUtluNJbd13\
+        4/tJS7SsVQepj5WztCO7TG1F8PapspUwtP1MVYwnSlcUfIKdzXOS0xZKBgyMUNGP\
+        Hgm+F6HmIcr9g+UQvIOlCsRnKPZzFBQ9RnbDhxSJITRNrw9FDKZJobq7nMWxM4Mp\
+        hQIDAQAB";
+
+    #[test]
+    fn test_sign_verify_raw_hash() {
+        use sha2::Sha256;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"signed by an HSM");
+
+        let sig = sign_raw_hash(Some(&mut rng), priv_key, &hashed).unwrap();
+        assert_eq!(
+            sig,
+            sign::<StdRng, _>(None, priv_key, None, &hashed).unwrap()
+        );
+        verify_raw_hash(&pub_key, &hashed, &sig).unwrap();
+
+        // There is no DigestInfo, so verifying with one fails, as does
+        // verifying a DigestInfo signature as a raw hash.
+        assert!(verify(&pub_key, Some(&Hash::SHA2_256), &hashed, &sig).is_err());
+        let with_prefix =
+            sign::<StdRng, _>(None, priv_key, Some(&Hash::SHA2_256), &hashed).unwrap();
+        assert!(verify_raw_hash(&pub_key, &hashed, &with_prefix).is_err());
+        assert!(verify_raw_hash(&pub_key, &Sha256::digest(b"other"), &sig).is_err());
+
+        match sign_raw_hash::<StdRng, _>(None, priv_key, &[0x55; 118]) {
+            Err(Error::MessageTooLong) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_message() {
+        use sha2::Sha256;
+
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let msg = b"verified in one step";
+        let padding = PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256));
+        let sig = priv_key.sign(padding, &Sha256::digest(msg)).unwrap();
+        test_util::assert_rejects_malformed_signatures(&pub_key, &sig, |sig| {
+            verify_message::<Sha256, _>(&pub_key, Hash::SHA2_256, msg, sig)
+        });
+
+        match verify_message::<Sha256, _>(&pub_key, Hash::SHA2_256, b"another message", &sig) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match verify_message::<Sha1, _>(&pub_key, Hash::SHA2_256, msg, &sig) {
+            Err(Error::DigestLength { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_verify_cert_signature() {
+        use sha2::Sha256;
+
+        let cert = base64::decode(DIGICERT_ROOT_G2).unwrap();
+        let issuer_der = base64::decode(DIGICERT_ROOT_G2_PUBLIC_KEY).unwrap();
+        let issuer = RSAPublicKey::from_pkcs8(&issuer_der).unwrap();
+
+        // Certificate ::= SEQUENCE (4 byte header) of a 634 byte TBSCertificate,
+        // the signatureAlgorithm and a 261 byte BIT STRING whose first content
+        // byte counts the unused bits.
+        assert_eq!(cert.len(), 914);
+        let tbs = &cert[4..638];
+        let sig = &cert[658..];
+        assert_eq!(sig.len(), issuer.size());
+
+        verify_cert_signature::<Sha256, _>(&issuer, Hash::SHA2_256, tbs, sig).unwrap();
+
+        let mut tampered = tbs.to_vec();
+        tampered[20] ^= 1;
+        match verify_cert_signature::<Sha256, _>(&issuer, Hash::SHA2_256, &tampered, sig) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match verify_cert_signature::<Sha1, _>(&issuer, Hash::SHA2_256, tbs, sig) {
+            Err(Error::DigestLength {
+                expected: 32,
+                got: 20,
+            }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
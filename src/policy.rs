@@ -0,0 +1,382 @@
+//! Heuristics for rejecting weak public keys.
+//!
+//! [`RSAPublicKey::security_check`](crate::RSAPublicKey::security_check) runs
+//! the checks enabled in a [`SecurityPolicy`] on a public key, typically one
+//! received from an untrusted peer, and reports the first one that fails as
+//! `Error::WeakKey`. None of the checks needs the private key, and passing
+//! them does not prove a key is strong, only that it is not weak in one of
+//! these well known ways.
+//!
+//! [`detect_shared_modulus`] looks across a whole keystore instead, for keys
+//! that share their modulus.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::errors::{Error, Result};
+use crate::key::PublicKeyParts;
+
+/// Smallest modulus, in bits, the `*_with_policy` key constructors accept,
+/// whatever `SecurityPolicy::min_bits` says. Shorter moduli fail with
+/// `Error::KeyTooSmall`, as no padding scheme fits a useful message in them.
+pub const MIN_KEY_BITS: usize = 512;
+
+/// Upper bound, exclusive, of the primes tried as factors of the modulus.
+const SMALL_FACTOR_BOUND: u32 = 1 << 16;
+
+/// Number of steps of Fermat's factorization method tried on the modulus.
+/// The first step already factors moduli whose primes agree in their upper
+/// half, later ones catch primes that are slightly further apart.
+const FERMAT_ROUNDS: usize = 100;
+
+/// Smallest public exponent accepted by the exponent check, as recommended by
+/// NIST SP 800-56B.
+const MIN_PUBLIC_EXPONENT: u64 = 65537;
+
+/// Small primes for the ROCA fingerprint of Nemec et al., "The Return of
+/// Coppersmith's Attack" (CVE-2017-15361).
+const ROCA_PRIMES: [u32; 38] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167,
+];
+
+/// Generator of the primes produced by the vulnerable library.
+const ROCA_GENERATOR: u32 = 65537;
+
+/// The checks [`RSAPublicKey::security_check`](crate::RSAPublicKey::security_check)
+/// runs. The default enables every check with a minimum of 2048 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityPolicy {
+    /// Minimum bit length of the modulus, 0 disables the check.
+    pub min_bits: usize,
+    /// Reject moduli with the fingerprint of the ROCA vulnerability.
+    pub check_roca: bool,
+    /// Reject moduli with a prime factor below 2^16.
+    pub check_small_factors: bool,
+    /// Reject moduli whose two primes are so close that Fermat's method
+    /// factors them.
+    pub check_close_primes: bool,
+    /// Reject public exponents that are even or smaller than 65537.
+    pub check_public_exponent: bool,
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        SecurityPolicy {
+            min_bits: 2048,
+            check_roca: true,
+            check_small_factors: true,
+            check_close_primes: true,
+            check_public_exponent: true,
+        }
+    }
+}
+
+/// The check of a [`SecurityPolicy`] a key failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeakKeyReason {
+    /// The modulus is shorter than `SecurityPolicy::min_bits`.
+    TooSmall { bits: usize, min_bits: usize },
+    /// The public exponent is even or smaller than 65537.
+    PublicExponent,
+    /// The modulus has a prime factor below 2^16.
+    SmallFactor { factor: u32 },
+    /// The modulus has the ROCA fingerprint.
+    Roca,
+    /// The primes of the modulus are close enough for Fermat's method.
+    ClosePrimes,
+}
+
+impl core::fmt::Display for WeakKeyReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            WeakKeyReason::TooSmall { bits, min_bits } => write!(
+                f,
+                "modulus of {} bits is shorter than {} bits",
+                bits, min_bits
+            ),
+            WeakKeyReason::PublicExponent => write!(f, "weak public exponent"),
+            WeakKeyReason::SmallFactor { factor } => {
+                write!(f, "modulus is divisible by {}", factor)
+            }
+            WeakKeyReason::Roca => write!(f, "modulus has the ROCA fingerprint"),
+            WeakKeyReason::ClosePrimes => write!(f, "primes are too close together"),
+        }
+    }
+}
+
+/// Runs the checks enabled in `policy` on `key`, cheapest first.
+pub(crate) fn check<K: PublicKeyParts>(key: &K, policy: &SecurityPolicy) -> Result<()> {
+    let weak = |reason| Err(Error::WeakKey { reason });
+    let n = key.n();
+
+    let bits = n.bits();
+    if bits < policy.min_bits {
+        return weak(WeakKeyReason::TooSmall {
+            bits,
+            min_bits: policy.min_bits,
+        });
+    }
+    if policy.check_public_exponent && !is_sane_public_exponent(key.e()) {
+        return weak(WeakKeyReason::PublicExponent);
+    }
+    if policy.check_small_factors {
+        if let Some(factor) = small_factor(n) {
+            return weak(WeakKeyReason::SmallFactor { factor });
+        }
+    }
+    if policy.check_roca && has_roca_fingerprint(n) {
+        return weak(WeakKeyReason::Roca);
+    }
+    if policy.check_close_primes && fermat_factors(n) {
+        return weak(WeakKeyReason::ClosePrimes);
+    }
+    Ok(())
+}
+
+/// Returns the index pairs `(i, j)`, `i < j`, of the keys in `keys` that share
+/// their modulus, in ascending order.
+///
+/// A message encrypted to two keys with the same modulus and coprime public
+/// exponents can be recovered without either private key, and anyone holding
+/// one of the private keys can factor the modulus and sign for the other.
+/// Copies of the same key are reported too.
+pub fn detect_shared_modulus<K: PublicKeyParts>(keys: &[K]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|&a, &b| keys[a].n().cmp(keys[b].n()).then(a.cmp(&b)));
+
+    // Equal moduli are now adjacent, and within a run the indices ascend.
+    let mut pairs = Vec::new();
+    let mut start = 0;
+    while start < order.len() {
+        let n = keys[order[start]].n();
+        let len = order[start..]
+            .iter()
+            .take_while(|&&k| keys[k].n() == n)
+            .count();
+        let run = &order[start..start + len];
+        for (x, &i) in run.iter().enumerate() {
+            pairs.extend(run[x + 1..].iter().map(|&j| (i, j)));
+        }
+        start += len;
+    }
+    pairs.sort();
+    pairs
+}
+
+fn is_sane_public_exponent(e: &BigUint) -> bool {
+    e % 2u32 == BigUint::one() && *e >= BigUint::from(MIN_PUBLIC_EXPONENT)
+}
+
+/// Returns the smallest prime factor of `n` below `SMALL_FACTOR_BOUND`,
+/// unless `n` is that prime itself.
+fn small_factor(n: &BigUint) -> Option<u32> {
+    let bound = SMALL_FACTOR_BOUND as usize;
+    let mut composite = vec![false; bound];
+    for p in 2..bound {
+        if composite[p] {
+            continue;
+        }
+        for multiple in (p * p..bound).step_by(p) {
+            composite[multiple] = true;
+        }
+        if (n % p as u32).is_zero() && *n != BigUint::from(p) {
+            return Some(p as u32);
+        }
+    }
+    None
+}
+
+/// Returns whether `n` modulo every prime in `ROCA_PRIMES` lies in the
+/// subgroup generated by 65537, as is the case for all moduli made of primes
+/// of the form `k * M + (65537^a mod M)`.
+fn has_roca_fingerprint(n: &BigUint) -> bool {
+    ROCA_PRIMES.iter().all(|&p| {
+        let residue = (n % p).to_u32().expect("reduced modulo a u32");
+        let generator = ROCA_GENERATOR % p;
+        let mut power = 1;
+        loop {
+            if power == residue {
+                return true;
+            }
+            power = power * generator % p;
+            if power == 1 {
+                return false;
+            }
+        }
+    })
+}
+
+/// Tries `FERMAT_ROUNDS` steps of Fermat's factorization method, which finds
+/// `n = (a - b) * (a + b)` quickly if its two factors are close to `sqrt(n)`.
+fn fermat_factors(n: &BigUint) -> bool {
+    let mut a = n.sqrt();
+    if &a * &a < *n {
+        a += 1u32;
+    }
+
+    for _ in 0..FERMAT_ROUNDS {
+        let b_squared = &a * &a - n;
+        let b = b_squared.sqrt();
+        if &b * &b == b_squared {
+            // n = (a - b) * (a + b), which only counts if a - b > 1.
+            return &a - &b > BigUint::one();
+        }
+        a += 1u32;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::is_probably_prime;
+    use crate::{test_util, RSAPublicKey};
+
+    use num_bigint::RandBigInt;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const NOTHING: SecurityPolicy = SecurityPolicy {
+        min_bits: 0,
+        check_roca: false,
+        check_small_factors: false,
+        check_close_primes: false,
+        check_public_exponent: false,
+    };
+
+    fn expect_weak(key: &RSAPublicKey, policy: SecurityPolicy, expected: WeakKeyReason) {
+        match key.security_check(policy) {
+            Err(Error::WeakKey { reason }) if reason == expected => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // Every other check lets the key through.
+        key.security_check(NOTHING).unwrap();
+    }
+
+    fn random_odd(rng: &mut StdRng, bits: usize) -> BigUint {
+        rng.gen_biguint(bits) | BigUint::one() | (BigUint::one() << (bits - 1))
+    }
+
+    fn next_prime(mut candidate: BigUint) -> BigUint {
+        candidate |= BigUint::one();
+        while !is_probably_prime(&candidate, candidate.bits()) {
+            candidate += 2u32;
+        }
+        candidate
+    }
+
+    #[test]
+    fn test_clean_key_passes() {
+        let key = test_util::test_key_2048().to_public_key();
+        key.security_check(SecurityPolicy::default()).unwrap();
+    }
+
+    #[test]
+    fn test_too_small() {
+        let key = test_util::test_key_1024().to_public_key();
+        let policy = SecurityPolicy::default();
+        expect_weak(
+            &key,
+            policy,
+            WeakKeyReason::TooSmall {
+                bits: 1024,
+                min_bits: 2048,
+            },
+        );
+        key.security_check(SecurityPolicy {
+            min_bits: 1024,
+            ..policy
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_public_exponent() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = random_odd(&mut rng, 2048);
+        let policy = SecurityPolicy {
+            check_public_exponent: true,
+            ..NOTHING
+        };
+
+        for &e in [3u32, 17, 65536].iter() {
+            let key = RSAPublicKey::new(n.clone(), e.into()).unwrap();
+            expect_weak(&key, policy, WeakKeyReason::PublicExponent);
+        }
+        let key = RSAPublicKey::new(n, 65537u32.into()).unwrap();
+        key.security_check(policy).unwrap();
+    }
+
+    #[test]
+    fn test_small_factor() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let large = next_prime(random_odd(&mut rng, 1008));
+        let policy = SecurityPolicy {
+            check_small_factors: true,
+            ..NOTHING
+        };
+
+        for &factor in [3u32, 65521].iter() {
+            let key = RSAPublicKey::new(&large * factor, 65537u32.into()).unwrap();
+            expect_weak(&key, policy, WeakKeyReason::SmallFactor { factor });
+        }
+    }
+
+    #[test]
+    fn test_roca() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let primorial = ROCA_PRIMES.iter().fold(BigUint::one(), |acc, &p| acc * p);
+
+        // Moduli of vulnerable keys are congruent to a power of 65537 modulo
+        // the product of the ROCA primes.
+        let power = BigUint::from(ROCA_GENERATOR).modpow(&rng.gen_biguint(64), &primorial);
+        let mut n = rng.gen_biguint(2048 - primorial.bits()) * &primorial + power;
+        if (&n % 2u32).is_zero() {
+            n += &primorial;
+        }
+        let key = RSAPublicKey::new(n, 65537u32.into()).unwrap();
+        let policy = SecurityPolicy {
+            check_roca: true,
+            ..NOTHING
+        };
+        expect_weak(&key, policy, WeakKeyReason::Roca);
+
+        let clean = test_util::test_key_1024().to_public_key();
+        clean.security_check(policy).unwrap();
+    }
+
+    #[test]
+    fn test_close_primes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let p = next_prime(random_odd(&mut rng, 1024));
+        let q = next_prime(&p + (rng.gen_biguint(400) | BigUint::one()));
+        let key = RSAPublicKey::new(&p * &q, 65537u32.into()).unwrap();
+        let policy = SecurityPolicy {
+            check_close_primes: true,
+            ..NOTHING
+        };
+        expect_weak(&key, policy, WeakKeyReason::ClosePrimes);
+
+        // A prime only has the trivial representation n = 1 * n.
+        let prime = RSAPublicKey::new(p, 65537u32.into()).unwrap();
+        prime.security_check(policy).unwrap();
+    }
+
+    #[test]
+    fn test_detect_shared_modulus() {
+        let a = test_util::test_key_1024().to_public_key();
+        let b = test_util::test_key_2048().to_public_key();
+        let same_n = |e: u32| RSAPublicKey::new(a.n().clone(), BigUint::from(e)).unwrap();
+        let keys = vec![a.clone(), b.clone(), same_n(3), same_n(17), b.clone()];
+
+        assert_eq!(
+            detect_shared_modulus(&keys),
+            vec![(0, 2), (0, 3), (1, 4), (2, 3)]
+        );
+        assert!(detect_shared_modulus(&[a, b]).is_empty());
+        assert!(detect_shared_modulus::<RSAPublicKey>(&[]).is_empty());
+    }
+}
@@ -1,35 +1,520 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::marker::PhantomData;
 
-use digest::DynDigest;
-use rand::{Rng, RngCore};
-use subtle::ConstantTimeEq;
+use digest::{Digest, DynDigest};
+#[cfg(feature = "hmac")]
+use digest::{BlockInput, FixedOutput, Reset, Update};
+#[cfg(feature = "hmac")]
+use hmac::{Hmac, Mac, NewMac};
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
 
 use crate::algorithms::mgf1_xor;
 use crate::errors::{Error, Result};
-use crate::key::{PrivateKey, PublicKey};
+use crate::hash::{self, Hash};
+use crate::internals::{self, left_pad, Barrett};
+use crate::key::{self, PrivateExponentiation, PrivateKey, PublicKey, PublicKeyParts, RSAPrivateKey};
+use crate::policy::{self, SecurityPolicy};
+use crate::sizes;
 
+/// Verifies a RSASSA-PSS signature of `hashed`, recovering the salt length
+/// from the signature.
+///
+/// Verifiers that know which salt length the signer uses, like JOSE which
+/// fixes it to the hash length, should rather require exactly that length
+/// with [`verify_pss_ex`] and `SaltPolicy::Exactly`, so that signatures with
+/// other salts are rejected.
 pub fn verify<PK: PublicKey>(
     pub_key: &PK,
     hashed: &[u8],
     sig: &[u8],
     digest: &mut dyn DynDigest,
 ) -> Result<()> {
+    verify_salt_len(pub_key, hashed, sig, None, digest).map(|_| ())
+}
+
+/// Hashes `msg` with `H` and verifies the RSASSA-PSS signature `sig` of it,
+/// with `H` for MGF1 and the salt length recovered as in [`verify`].
+///
+/// `sig` has to be exactly as long as the modulus and smaller than it, and
+/// every failure is `Error::Verification`.
+pub fn verify_message<H: Digest + DynDigest, PK: PublicKey>(
+    pub_key: &PK,
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    key::validate_representative(pub_key, sig).map_err(|_| Error::Verification)?;
+    let hashed = <H as Digest>::digest(msg);
+    verify_salt_len(pub_key, &hashed, sig, None, &mut <H as Digest>::new())
+        .map(|_| ())
+        .map_err(|_| Error::Verification)
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed` like [`verify`], with the hash
+/// function chosen at runtime and passed as a trait object in `hash`, which
+/// is used for MGF1 as well, see `Hash::new_dyn_digest`.
+///
+/// Unlike [`verify`] a `hashed` of the wrong length for `hash` fails with
+/// `Error::DigestLength`, as the hash of an untrusted header that names the
+/// wrong algorithm is a mistake of the caller rather than a bad signature.
+pub fn verify_pss_dyn<PK: PublicKey>(
+    pub_key: &PK,
+    hash: &mut dyn DynDigest,
+    hashed: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    hash::check_len(hashed, hash.output_size())?;
+    verify(pub_key, hashed, sig, hash)
+}
+
+/// Fails with `Error::SignatureLength` unless `sig` is exactly as long as the
+/// modulus of `pub_key`. The length of a signature is public, so reporting it
+/// separately from `Error::Verification` does not help an attacker.
+pub(crate) fn check_sig_len<K: PublicKeyParts>(pub_key: &K, sig: &[u8]) -> Result<()> {
     if sig.len() != pub_key.size() {
+        return Err(Error::SignatureLength {
+            expected: pub_key.size(),
+            got: sig.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Verifies a signature and returns the length of its salt, which is
+/// recovered from the signature if `s_len` is `None`.
+pub(crate) fn verify_salt_len<PK: PublicKey>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+    s_len: Option<usize>,
+    digest: &mut dyn DynDigest,
+) -> Result<usize> {
+    check_sig_len(pub_key, sig)?;
+
+    let em_bits = sizes::em_bits(pub_key.n_bits())?;
+    let em_len = sizes::em_len(em_bits);
+    let _span = debug_span!(
+        "pss_verify",
+        em_bits,
+        em_len,
+        h_len = digest.output_size(),
+        expected_salt_len = ?s_len
+    );
+    let mut em = pub_key.raw_encryption_primitive(sig, em_len)?;
+
+    let result = emsa_pss_verify(hashed, &mut em, em_bits, s_len, digest);
+    debug_event!(ok = result.is_ok(), salt_len = ?result.as_ref().ok(), "emsa_pss_verify");
+    result
+}
+
+/// Verifies a RSASSA-PSS signature like [`verify`], using `H` as the hash
+/// function and `scratch` to hold the encoded message.
+///
+/// The encoded message is written straight into `scratch` instead of into
+/// freshly allocated buffers, so passing the same `scratch` to repeated calls
+/// saves two allocations per verification once it has grown to the size of
+/// the modulus. The contents of `scratch` on return are unspecified.
+pub fn verify_with_scratch<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+    scratch: &mut Vec<u8>,
+) -> Result<()> {
+    check_sig_len(pub_key, sig)?;
+
+    let em_bits = sizes::em_bits(pub_key.n_bits())?;
+    let em_len = sizes::em_len(em_bits);
+    if !bool::from(internals::ct_less_than(sig, pub_key.n())) {
         return Err(Error::Verification);
     }
+    let m = internals::encrypt(pub_key, &BigUint::from_bytes_be(sig));
 
-    let em_bits = pub_key.n().bits() - 1;
-    let em_len = (em_bits + 7) / 8;
+    // The same check as in `raw_encryption_primitive`.
+    if m.bits() > em_len * 8 {
+        return Err(Error::Verification);
+    }
+
+    // Write m big-endian into scratch, left padded with zeroes like
+    // `left_pad(&m.to_bytes_be(), em_len)` would.
+    scratch.clear();
+    scratch.resize(em_len, 0);
+    for i in 0..m.bits().div_ceil(64) {
+        for (j, byte) in m.get_limb(i).to_le_bytes().iter().enumerate() {
+            if let Some(pos) = (em_len - 1).checked_sub(i * 8 + j) {
+                scratch[pos] = *byte;
+            }
+        }
+    }
+
+    emsa_pss_verify(hashed, scratch, em_bits, None, &mut <H as Digest>::new()).map(|_| ())
+}
+
+/// Verifies a RSASSA-PSS signature like [`verify`], using `H` as the hash
+/// function, with the signature representative `s` given as an integer.
+///
+/// This saves protocols that keep signatures as integers the round trip
+/// through bytes. As with the byte based functions `s` must be smaller than
+/// the modulus, there is no length to check.
+pub fn verify_int<K: PublicKeyParts, H: Digest + DynDigest>(
+    pub_key: &K,
+    hashed: &[u8],
+    s: &BigUint,
+) -> Result<()> {
+    if s >= pub_key.n() {
+        return Err(Error::Verification);
+    }
+
+    let em_bits = sizes::em_bits(pub_key.n_bits())?;
+    let em_len = sizes::em_len(em_bits);
+    let m = internals::encrypt(pub_key, s);
+    // The same check as in `raw_encryption_primitive`.
+    let m_bytes = m.to_bytes_be();
+    if m_bytes.len() > em_len {
+        return Err(Error::Verification);
+    }
+
+    let mut em = left_pad(&m_bytes, em_len);
+    emsa_pss_verify(hashed, &mut em, em_bits, None, &mut <H as Digest>::new()).map(|_| ())
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed` using `H` as the hash
+/// function, also accepting signatures with the non-standard data block
+/// layout of a known buggy signer.
+///
+/// **This is a compatibility workaround and not RFC 8017 compliant.** Use it
+/// only to verify historical signatures from that signer, [`verify`] stays
+/// strict.
+///
+/// RFC 8017 puts the zero padding string PS before the salt:
+///
+/// ```text
+/// DB = PS || 0x01 || salt
+/// ```
+///
+/// The legacy signer put the salt first, so its data block is
+///
+/// ```text
+/// DB = salt || 0x01 || PS
+/// ```
+///
+/// with PS again all zero bytes. Everything else, the masking of DB with
+/// MGF1, `H = Hash(0x00 * 8 || mHash || salt)` and the trailing 0xbc, is as
+/// in the standard. The salt length is recovered from the position of the
+/// last non-zero byte of DB, which must be the 0x01 separator. Signatures with
+/// the standard layout are accepted as well.
+pub fn verify_pss_compat<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    check_sig_len(pub_key, sig)?;
+
+    let em_bits = sizes::em_bits(pub_key.n_bits())?;
+    let em_len = sizes::em_len(em_bits);
     let mut em = pub_key.raw_encryption_primitive(sig, em_len)?;
+    let mut legacy_em = em.clone();
+
+    let mut digest = <H as Digest>::new();
+    if emsa_pss_verify(hashed, &mut em, em_bits, None, &mut digest).is_ok() {
+        return Ok(());
+    }
+    emsa_pss_verify_legacy(hashed, &mut legacy_em, em_bits, &mut <H as Digest>::new())
+}
+
+/// Checks `em` against `m_hash` for an encoded message with the salt at the
+/// front of DB, see [`verify_pss_compat`].
+fn emsa_pss_verify_legacy(
+    m_hash: &[u8],
+    em: &mut [u8],
+    em_bits: usize,
+    hash: &mut dyn DynDigest,
+) -> Result<()> {
+    let h_len = hash.output_size();
+    if m_hash.len() != h_len || em.len() < h_len + 2 {
+        return Err(Error::Verification);
+    }
+
+    let (db, h) = unmask_db(em, em_bits, hash)?;
+
+    // DB = salt || 0x01 || PS, find the separator after the salt.
+    let separator = db
+        .iter()
+        .rposition(|b| *b != 0)
+        .ok_or(Error::Verification)?;
+    if db[separator] != 0x01 {
+        return Err(Error::Verification);
+    }
+
+    check_salted_hash(m_hash, &db[..separator], h, hash)
+}
+
+/// Which salt lengths [`verify_pss_ex`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaltPolicy {
+    /// Any salt length, recovered from the signature.
+    Any,
+    /// Exactly the given salt length.
+    Exactly(usize),
+    /// The given salt length or longer.
+    AtLeast(usize),
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed`, using `H` as the hash
+/// function, and returns the length of the salt that was used.
+///
+/// The salt length must satisfy `policy`, otherwise `Error::Verification`
+/// is returned.
+pub fn verify_pss_ex<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+    policy: SaltPolicy,
+) -> Result<usize> {
+    let expected_salt_len = match policy {
+        SaltPolicy::Exactly(s_len) => Some(s_len),
+        SaltPolicy::Any | SaltPolicy::AtLeast(_) => None,
+    };
+    let s_len = verify_salt_len(
+        pub_key,
+        hashed,
+        sig,
+        expected_salt_len,
+        &mut <H as Digest>::new(),
+    )?;
+
+    match policy {
+        SaltPolicy::AtLeast(min_len) if s_len < min_len => Err(Error::Verification),
+        _ => Ok(s_len),
+    }
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed`, using `H` as the hash
+/// function, with exactly the parameters of `params`, as X.509 requires for
+/// certificates that carry `RSASSA-PSS-params` in their signature algorithm.
+///
+/// The salt must be `params.salt_len` bytes long, a signature with any other
+/// salt fails with `Error::Verification`. `hash` names the function `H`
+/// computes. Parameters that name another hash, that use MGF1 over another
+/// hash or that have a trailer field other than 1 are rejected with
+/// `Error::InvalidPaddingScheme` before the signature is looked at, as is a
+/// `hash` whose output length does not match `H`.
+pub fn verify_pss_with_params<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hash: Hash,
+    hashed: &[u8],
+    sig: &[u8],
+    params: &PssParams,
+) -> Result<()> {
+    if hash.size() != <H as Digest>::output_size()
+        || params.hash != hash
+        || params.mgf_hash != hash
+        || params.trailer_field != 1
+    {
+        return Err(Error::InvalidPaddingScheme);
+    }
+    verify_pss_ex::<_, H>(pub_key, hashed, sig, SaltPolicy::Exactly(params.salt_len)).map(|_| ())
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed`, using `H` as the hash
+/// function, from a key of at least `min_bits` bits.
+///
+/// Shorter keys are rejected with `Error::WeakKey` before the signature is
+/// looked at, so a valid signature does not make up for a weak key. The
+/// salt length is recovered from the signature, as with [`verify`].
+pub fn verify_pss_min_bits<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+    min_bits: usize,
+) -> Result<()> {
+    let policy = SecurityPolicy {
+        min_bits,
+        check_roca: false,
+        check_small_factors: false,
+        check_close_primes: false,
+        check_public_exponent: false,
+    };
+    policy::check(pub_key, &policy)?;
+    verify_pss_ex::<_, H>(pub_key, hashed, sig, SaltPolicy::Any).map(|_| ())
+}
+
+/// Verifies a RSASSA-PSS signature over a message that is fed in chunks.
+///
+/// The message is hashed with `H` as it arrives, so neither the message nor
+/// its hash has to be buffered by the caller. As with [`verify`], the salt
+/// length is recovered from the signature.
+pub struct PssVerifier<'a, K: PublicKey, H: Digest + DynDigest + Clone> {
+    pub_key: &'a K,
+    hasher: H,
+}
+
+impl<'a, K: PublicKey, H: Digest + DynDigest + Clone> PssVerifier<'a, K, H> {
+    /// Creates a verifier for signatures made with the private half of `pub_key`.
+    pub fn new(pub_key: &'a K) -> Self {
+        PssVerifier {
+            pub_key,
+            hasher: <H as Digest>::new(),
+        }
+    }
+
+    /// Creates a verifier like [`new`](Self::new) that continues from
+    /// `hasher`, which may already have been fed the start of the message.
+    pub fn from_digest(pub_key: &'a K, hasher: H) -> Self {
+        PssVerifier { pub_key, hasher }
+    }
+
+    /// Feeds the next chunk of the message.
+    pub fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.hasher, data);
+    }
+
+    /// Verifies `sig` against everything fed through [`update`](Self::update) so far.
+    pub fn verify(&self, sig: &[u8]) -> Result<()> {
+        let hashed = Digest::finalize(self.hasher.clone());
+        verify(self.pub_key, &hashed, sig, &mut <H as Digest>::new())
+    }
+}
+
+/// Intermediate values of a successful RSASSA-PSS verification, returned by
+/// [`verify_pss_full`].
+///
+/// Meant for diagnostics, e.g. comparing what another implementation
+/// produced with what this one recovered, not for making decisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PssVerifyDetails {
+    /// The encoded message `EM = sig^e mod n`, as recovered from the
+    /// signature and before unmasking, `em_len` bytes long.
+    pub em: Vec<u8>,
+    /// The salt, recovered from the unmasked data block.
+    pub salt: Vec<u8>,
+    /// The length of `salt`.
+    pub salt_len: usize,
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed` like [`verify`], using `H`
+/// as the hash function, and returns the encoded message and salt it
+/// recovered.
+///
+/// Signatures that do not verify only yield `Error::Verification`, as with
+/// every other verification function.
+pub fn verify_pss_full<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+) -> Result<PssVerifyDetails> {
+    verify_details(pub_key, hashed, sig, &mut <H as Digest>::new())
+}
+
+fn verify_details<PK: PublicKey>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+    digest: &mut dyn DynDigest,
+) -> Result<PssVerifyDetails> {
+    check_sig_len(pub_key, sig)?;
+
+    let em_bits = sizes::em_bits(pub_key.n_bits())?;
+    let em_len = sizes::em_len(em_bits);
+    let recovered = pub_key.raw_encryption_primitive(sig, em_len)?;
+    let mut em = recovered.clone();
+    let salt_len = emsa_pss_verify(hashed, &mut em, em_bits, None, digest)?;
+
+    // DB = PS || 0x01 || salt was unmasked in place and ends right before H.
+    let db_len = em_len - digest.output_size() - 1;
+    Ok(PssVerifyDetails {
+        em: recovered,
+        salt: em[db_len - salt_len..db_len].to_vec(),
+        salt_len,
+    })
+}
+
+/// Two signatures over different messages that share a salt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaltReuse {
+    /// The shared salt.
+    pub salt: Vec<u8>,
+    /// Hash of the message of the signature the salt was first seen in.
+    pub first_hashed: Vec<u8>,
+    /// Hash of the message of the signature that reused it.
+    pub hashed: Vec<u8>,
+}
+
+/// Watches the RSASSA-PSS signatures of one key for reused salts.
+///
+/// Salts are recovered from the signatures, so the detector sees what any
+/// verifier sees. A signer that puts the same salt into signatures over
+/// different messages has a broken random number generator, so a reuse is
+/// worth an alert even though PSS itself stays secure. Empty salts carry no
+/// randomness to begin with and are not tracked, neither are signatures that
+/// do not verify.
+///
+/// Every salt seen is kept in memory together with its message hash.
+pub struct SaltReuseDetector<'a, K: PublicKey, H: Digest + DynDigest> {
+    pub_key: &'a K,
+    seen: BTreeMap<Vec<u8>, Vec<u8>>,
+    _hash: PhantomData<H>,
+}
+
+impl<'a, K: PublicKey, H: Digest + DynDigest> SaltReuseDetector<'a, K, H> {
+    /// Creates a detector for signatures made with the private half of
+    /// `pub_key` over messages hashed with `H`.
+    pub fn new(pub_key: &'a K) -> Self {
+        SaltReuseDetector {
+            pub_key,
+            seen: BTreeMap::new(),
+            _hash: PhantomData,
+        }
+    }
+
+    /// Verifies `sig` over `hashed` and records its salt.
+    ///
+    /// Returns the reuse if the salt was seen before in a signature over a
+    /// different message, and fails like [`verify`] if `sig` does not verify.
+    pub fn observe(&mut self, hashed: &[u8], sig: &[u8]) -> Result<Option<SaltReuse>> {
+        let salt = verify_details(self.pub_key, hashed, sig, &mut <H as Digest>::new())?.salt;
+        if salt.is_empty() {
+            return Ok(None);
+        }
+
+        match self.seen.get(&salt) {
+            Some(first_hashed) if first_hashed[..] != *hashed => Ok(Some(SaltReuse {
+                salt,
+                first_hashed: first_hashed.clone(),
+                hashed: hashed.to_vec(),
+            })),
+            Some(_) => Ok(None),
+            None => {
+                self.seen.insert(salt, hashed.to_vec());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Number of distinct salts seen so far.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
 
-    emsa_pss_verify(hashed, &mut em, em_bits, None, digest)
+    /// Returns `true` if no salt has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
 }
 
 /// SignPSS calculates the signature of hashed using RSASSA-PSS [1].
 /// Note that hashed must be the result of hashing the input message using the
 /// given hash function. The opts argument may be nil, in which case sensible
 /// defaults are used.
+///
+/// If `salt_len` is `None` the largest salt that fits is used, which is
+/// `em_len - h_len - 2` bytes with `em_len = ceil((n_bits - 1) / 8)`. This is
+/// the same length [`verify`] recovers when detecting the salt length from a
+/// signature, and what OpenSSL calls `max`.
 pub fn sign<T: RngCore + ?Sized, S: Rng, SK: PrivateKey>(
     rng: &mut T,
     blind_rng: Option<&mut S>,
@@ -38,29 +523,408 @@ pub fn sign<T: RngCore + ?Sized, S: Rng, SK: PrivateKey>(
     salt_len: Option<usize>,
     digest: &mut dyn DynDigest,
 ) -> Result<Vec<u8>> {
-    let salt_len = salt_len.unwrap_or_else(|| priv_key.size() - 2 - digest.output_size());
+    let salt = generate_salt(rng, priv_key, salt_len, digest.output_size())?;
+    sign_pss_with_salt(blind_rng, priv_key, hashed, &salt, digest)
+}
+
+/// Calculates an RSASSA-PSS signature of `hashed` like [`sign`] with a fresh
+/// salt of `salt_len` bytes, using `H` as the hash function, and returns the
+/// signature together with the salt.
+///
+/// `rng` is used both for the salt and for blinding. The salt is not secret,
+/// anyone holding the public key can recover it from the signature, so it can
+/// be written to audit logs or handed to a verifier that pins it.
+pub fn sign_pss_with_salt_out<H: Digest + DynDigest, R: Rng, SK: PrivateKey>(
+    priv_key: &SK,
+    rng: &mut R,
+    hashed: &[u8],
+    salt_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let salt = generate_salt(rng, priv_key, Some(salt_len), <H as Digest>::output_size())?;
+    let sig = sign_pss_with_salt(
+        Some(rng),
+        priv_key,
+        hashed,
+        &salt,
+        &mut <H as Digest>::new(),
+    )?;
+    Ok((sig, salt))
+}
+
+/// Calculates an RSASSA-PSS signature like [`sign`] of the message fed into
+/// `digest` so far, using `H` as the hash function.
+///
+/// `digest` is finalized here, so content already hashed elsewhere, e.g. a
+/// header streamed in before the body, does not have to be hashed again.
+pub fn sign_digest<T: RngCore + ?Sized, S: Rng, SK: PrivateKey, H: Digest + DynDigest>(
+    rng: &mut T,
+    blind_rng: Option<&mut S>,
+    priv_key: &SK,
+    digest: H,
+    salt_len: Option<usize>,
+) -> Result<Vec<u8>> {
+    let hashed = Digest::finalize(digest);
+    sign(
+        rng,
+        blind_rng,
+        priv_key,
+        &hashed,
+        salt_len,
+        &mut <H as Digest>::new(),
+    )
+}
+
+/// Verifies a RSASSA-PSS signature like [`verify`] of the message fed into
+/// `digest` so far, using `H` as the hash function.
+pub fn verify_digest<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    digest: H,
+    sig: &[u8],
+) -> Result<()> {
+    let hashed = Digest::finalize(digest);
+    verify(pub_key, &hashed, sig, &mut <H as Digest>::new())
+}
+
+/// Draws a salt of `salt_len` bytes from `rng`, or of the largest length that
+/// fits the key alongside a hash of `h_len` bytes if `salt_len` is `None`.
+///
+/// A salt that does not fit fails with `Error::KeyTooSmall` before anything
+/// is allocated, as `emsa_pss_encode` would reject it anyway.
+fn generate_salt<T: RngCore + ?Sized, K: PublicKeyParts>(
+    rng: &mut T,
+    key: &K,
+    salt_len: Option<usize>,
+    h_len: usize,
+) -> Result<Vec<u8>> {
+    // The encoded message is one bit shorter than the modulus, which costs a
+    // whole byte when the modulus bit length is one more than a multiple of 8.
+    let em_len = sizes::em_len(sizes::em_bits(key.n_bits())?);
+    let max_salt_len = em_len.checked_sub(2 + h_len).ok_or(Error::KeyTooSmall)?;
+    let salt_len = match salt_len {
+        Some(salt_len) if salt_len > max_salt_len => return Err(Error::KeyTooSmall),
+        Some(salt_len) => salt_len,
+        None => max_salt_len,
+    };
 
     let mut salt = vec![0; salt_len];
     rng.fill(&mut salt[..]);
+    Ok(salt)
+}
 
-    sign_pss_with_salt(blind_rng, priv_key, hashed, &salt, digest)
+/// Calculates a reproducible RSASSA-PSS signature of `hashed`, using an empty
+/// salt and no blinding.
+///
+/// The same key and digest always produce the same signature, which makes
+/// this useful for golden-file tests. The signature verifies with [`verify`]
+/// as usual. Without a salt the security proof of PSS no longer applies and
+/// without blinding the private key operation is exposed to timing attacks,
+/// so prefer [`sign`] for anything but tests.
+pub fn sign_deterministic_pss<H: Digest + DynDigest, SK: PrivateKey>(
+    priv_key: &SK,
+    hashed: &[u8],
+) -> Result<Vec<u8>> {
+    sign_pss_with_salt::<StdRng, _>(None, priv_key, hashed, &[], &mut <H as Digest>::new())
+}
+
+/// Domain separation label for the salt derivation of [`sign_deterministic_salt`].
+#[cfg(feature = "hmac")]
+const DETERMINISTIC_SALT_LABEL: &[u8] = b"RSASSA-PSS deterministic salt";
+
+/// Calculates an RSASSA-PSS signature of `hashed` with a salt derived from the
+/// private key and `hashed` instead of drawn from an rng, similar to what
+/// RFC 6979 does for ECDSA nonces.
+///
+/// With `k` the length of the modulus in bytes and `H` the hash function,
+/// the salt is
+///
+/// ```text
+/// K    = H(I2OSP(d, k))
+/// salt = HMAC-H(K, "RSASSA-PSS deterministic salt" || hashed)
+/// ```
+///
+/// and thus as long as the output of `H`. The same key and hash always give
+/// the same signature, but without knowing `d` the salt is as unpredictable as
+/// a random one, unlike the empty salt of [`sign_deterministic_pss`]. The
+/// signature is a regular PSS signature for [`verify`]. If `blind_rng` is
+/// passed, it is used for RSA blinding, which does not change the result.
+///
+/// Fails with `Error::KeyTooSmall` if the key cannot hold a salt that long.
+/// Only available with the `hmac` feature.
+#[cfg(feature = "hmac")]
+pub fn sign_deterministic_salt<H, S: Rng>(
+    blind_rng: Option<&mut S>,
+    priv_key: &RSAPrivateKey,
+    hashed: &[u8],
+) -> Result<Vec<u8>>
+where
+    H: Digest + DynDigest + Update + BlockInput + FixedOutput + Reset + Default + Clone,
+{
+    let mut d = left_pad(&priv_key.d().to_bytes_be(), priv_key.size());
+    let mut key = <H as Digest>::digest(&d);
+    d.zeroize();
+
+    let mut mac = Hmac::<H>::new_varkey(&key).expect("HMAC accepts keys of any size");
+    key[..].zeroize();
+    mac.update(DETERMINISTIC_SALT_LABEL);
+    mac.update(hashed);
+    let mut salt = mac.finalize().into_bytes();
+
+    let sig = sign_pss_with_salt(
+        blind_rng,
+        priv_key,
+        hashed,
+        &salt,
+        &mut <H as Digest>::new(),
+    );
+    salt[..].zeroize();
+    sig
+}
+
+/// Signs a digest that was computed outside of the `digest` crate, e.g. by an
+/// HSM, using RSASSA-PSS.
+///
+/// `hashed` is the message hash and `hash_output_size` the output size of
+/// the algorithm that produced it. `digest` is only used internally, for
+/// hashing the salted message representative and for MGF1. RSASSA-PSS
+/// requires all three to use the same hash function, so `digest` must be an
+/// instance of the algorithm that produced `hashed`; a verifier will
+/// otherwise reject the signature. As a basic check, `Error::DigestLength` is
+/// returned unless `hashed.len()` is `hash_output_size`, and
+/// `Error::InputNotHashed` unless `digest.output_size()` is.
+///
+/// If `salt_len` is `None` the largest salt that fits the key is used, as with
+/// [`sign`].
+pub fn sign_prehashed<T: RngCore + ?Sized, S: Rng, SK: PrivateKey>(
+    rng: &mut T,
+    blind_rng: Option<&mut S>,
+    priv_key: &SK,
+    hashed: &[u8],
+    hash_output_size: usize,
+    salt_len: Option<usize>,
+    digest: &mut dyn DynDigest,
+) -> Result<Vec<u8>> {
+    hash::check_len(hashed, hash_output_size)?;
+    if digest.output_size() != hash_output_size {
+        return Err(Error::InputNotHashed);
+    }
+
+    sign(rng, blind_rng, priv_key, hashed, salt_len, digest)
 }
 
 /// signPSSWithSalt calculates the signature of hashed using PSS [1] with specified salt.
 /// Note that hashed must be the result of hashing the input message using the
 /// given hash function. salt is a random sequence of bytes whose length will be
 /// later used to verify the signature.
-fn sign_pss_with_salt<T: Rng, SK: PrivateKey>(
+pub(crate) fn sign_pss_with_salt<T: Rng, SK: PrivateKey>(
     blind_rng: Option<&mut T>,
     priv_key: &SK,
     hashed: &[u8],
     salt: &[u8],
     digest: &mut dyn DynDigest,
 ) -> Result<Vec<u8>> {
-    let em_bits = priv_key.n().bits() - 1;
-    let em = emsa_pss_encode(hashed, em_bits, salt, digest)?;
+    let em = encode_for_key(priv_key, hashed, salt, digest)?;
+    let rng = blind_rng.map(|rng| rng as &mut dyn RngCore);
+    // `raw_decryption_primitive` already checks its result.
+    let s = priv_key.modpow_private(rng, &BigUint::from_bytes_be(&em))?;
+    Ok(to_signature(priv_key, s))
+}
+
+/// Calculates an RSASSA-PSS signature of `hashed` like [`sign`], but leaves
+/// the private key operation to `priv_key`, see `PrivateExponentiation`.
+///
+/// The salt is drawn from `rng`, if `salt_len` is `None` the largest salt
+/// that fits the key is used. `rng` is also passed on to `modpow_private` for
+/// blinding. Its result is checked against the public key, so that a faulty
+/// exponentiation, which can give away the factors of the modulus, fails
+/// with `Error::Internal` instead of being returned as a signature.
+pub fn sign_with_exponentiation<T: RngCore + ?Sized, PE: PrivateExponentiation>(
+    rng: &mut T,
+    priv_key: &PE,
+    hashed: &[u8],
+    salt_len: Option<usize>,
+    digest: &mut dyn DynDigest,
+) -> Result<Vec<u8>> {
+    let salt = generate_salt(rng, priv_key, salt_len, digest.output_size())?;
+    let em = encode_for_key(priv_key, hashed, &salt, digest)?;
+    let mut rng = rng;
+    let m = BigUint::from_bytes_be(&em);
+    let mut s = priv_key.modpow_private(Some(&mut rng), &m)?;
+    if &s >= priv_key.n() || s.modpow(priv_key.e(), priv_key.n()) != m {
+        s.zeroize();
+        return Err(Error::Internal);
+    }
+    Ok(to_signature(priv_key, s))
+}
+
+/// Left pads the signature representative `s` to the size of the key and
+/// zeroizes it.
+fn to_signature<K: PublicKeyParts + ?Sized>(key: &K, mut s: BigUint) -> Vec<u8> {
+    let mut s_bytes = s.to_bytes_be();
+    let sig = left_pad(&s_bytes, key.size());
+    s.zeroize();
+    s_bytes.zeroize();
+    sig
+}
+
+/// Signs all of `hashes`, see `RSAPrivateKey::sign_pss_batch`.
+pub(crate) fn sign_batch<H: Digest + DynDigest, R: Rng>(
+    priv_key: &RSAPrivateKey,
+    rng: &mut R,
+    hashes: &[&[u8]],
+    salt_len: usize,
+) -> Result<Vec<Vec<u8>>> {
+    let barrett = Barrett::new(priv_key.n());
+    let mut digest = <H as Digest>::new();
+    let h_len = <H as Digest>::output_size();
+
+    let mut sigs = Vec::with_capacity(hashes.len());
+    for hashed in hashes {
+        let salt = generate_salt(rng, priv_key, Some(salt_len), h_len)?;
+        let em = encode_for_key(priv_key, hashed, &salt, &mut digest)?;
+        let c = BigUint::from_bytes_be(&em);
+        let mut s = internals::decrypt_and_check_with(&barrett, Some(&mut *rng), priv_key, &c)?;
+        let mut s_bytes = s.to_bytes_be();
+        sigs.push(left_pad(&s_bytes, priv_key.size()));
+        s.zeroize();
+        s_bytes.zeroize();
+    }
+    Ok(sigs)
+}
+
+/// EMSA-PSS encodes `hashed` for a key with the modulus of `key`.
+fn encode_for_key<K: PublicKeyParts>(
+    key: &K,
+    hashed: &[u8],
+    salt: &[u8],
+    digest: &mut dyn DynDigest,
+) -> Result<Vec<u8>> {
+    let em_bits = sizes::em_bits(key.n_bits())?;
+    let _span = debug_span!(
+        "pss_sign",
+        em_bits,
+        h_len = digest.output_size(),
+        salt_len = salt.len()
+    );
+    emsa_pss_encode(hashed, em_bits, salt, digest)
+}
+
+/// Returns the smallest modulus size in bits that a RSASSA-PSS signature
+/// with `H` as the hash function and a salt of `salt_len` bytes can be made
+/// with.
+///
+/// The encoded message needs `em_bits >= 8 * h_len + 8 * salt_len + 9`, RFC
+/// 8017 section 9.1, and `em_bits` is one less than the modulus size. Smaller
+/// keys fail to sign with `Error::KeyTooSmall`.
+///
+/// The often quoted `8 * (h_len + salt_len + 2) + 1` counts `em_len` in
+/// whole bytes plus the leading bit and so asks for 7 bits more than the
+/// encoding needs, 529 instead of 522 for SHA-256 with a 32 byte salt. This
+/// returns the exact bound, so that a key of exactly this size signs and one
+/// bit less does not.
+pub fn min_key_bits_for_pss<H: Digest>(salt_len: usize) -> usize {
+    8 * (<H as Digest>::output_size() + salt_len) + 10
+}
+
+/// Calculates an RSASSA-PSS signature of `hashed` like
+/// [`sign_pss_with_salt_out`], with a salt as long as the output of `H`.
+///
+/// That is the salt length JOSE (RFC 7518) and TLS 1.3 (RFC 8446) require,
+/// see [`verify_pss_hash_len_salt`].
+pub fn sign_pss_hash_len_salt<H: Digest + DynDigest, R: Rng, SK: PrivateKey>(
+    priv_key: &SK,
+    rng: &mut R,
+    hashed: &[u8],
+) -> Result<Vec<u8>> {
+    let salt_len = <H as Digest>::output_size();
+    sign_pss_with_salt_out::<H, _, _>(priv_key, rng, hashed, salt_len).map(|(sig, _)| sig)
+}
+
+/// Verifies a RSASSA-PSS signature of `hashed` made with a salt as long as
+/// the output of `H`, as by [`sign_pss_hash_len_salt`]. Signatures with any
+/// other salt length fail with `Error::Verification`.
+pub fn verify_pss_hash_len_salt<PK: PublicKey, H: Digest + DynDigest>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    let policy = SaltPolicy::Exactly(<H as Digest>::output_size());
+    verify_pss_ex::<_, H>(pub_key, hashed, sig, policy).map(|_| ())
+}
+
+/// Calculates an RSASSA-PSS signature of `hashed` like
+/// [`sign_pss_hash_len_salt`], and returns it encoded as unpadded base64url,
+/// as JOSE carries signatures.
+pub fn sign_pss_b64url<H: Digest + DynDigest, R: Rng, SK: PrivateKey>(
+    priv_key: &SK,
+    rng: &mut R,
+    hashed: &[u8],
+) -> Result<String> {
+    let sig = sign_pss_hash_len_salt::<H, _, _>(priv_key, rng, hashed)?;
+    Ok(base64::encode_config(&sig, base64::URL_SAFE_NO_PAD))
+}
+
+/// Verifies an unpadded base64url encoded RSASSA-PSS signature of `hashed`,
+/// as made by [`sign_pss_b64url`], requiring a salt as long as the output of
+/// `H`.
+///
+/// Fails with `Error::ParseError` if `sig` is not valid unpadded base64url,
+/// which catches signatures encoded with the standard alphabet or padding.
+pub fn verify_pss_b64url<H: Digest + DynDigest, PK: PublicKey>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &str,
+) -> Result<()> {
+    let sig =
+        base64::decode_config(sig, base64::URL_SAFE_NO_PAD).map_err(|_| Error::ParseError {
+            reason: "signature is not unpadded base64url".to_string(),
+        })?;
+    verify_pss_hash_len_salt::<_, H>(pub_key, hashed, &sig)
+}
+
+/// Like [`sign_pss_b64url`], but returns the signature as lowercase hex.
+pub fn sign_pss_hex<H: Digest + DynDigest, R: Rng, SK: PrivateKey>(
+    priv_key: &SK,
+    rng: &mut R,
+    hashed: &[u8],
+) -> Result<String> {
+    let sig = sign_pss_hash_len_salt::<H, _, _>(priv_key, rng, hashed)?;
+    Ok(hex_encode(&sig))
+}
+
+/// Like [`verify_pss_b64url`], but takes the signature as hex, in either
+/// case. Fails with `Error::ParseError` if `sig` is not valid hex.
+pub fn verify_pss_hex<H: Digest + DynDigest, PK: PublicKey>(
+    pub_key: &PK,
+    hashed: &[u8],
+    sig: &str,
+) -> Result<()> {
+    let sig = hex_decode(sig)?;
+    verify_pss_hash_len_salt::<_, H>(pub_key, hashed, &sig)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(2 * bytes.len());
+    for b in bytes {
+        out.push(DIGITS[usize::from(b >> 4)] as char);
+        out.push(DIGITS[usize::from(b & 0x0f)] as char);
+    }
+    out
+}
 
-    priv_key.raw_decryption_primitive(blind_rng, &em, priv_key.size())
+fn hex_decode(hex: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::ParseError {
+        reason: "signature is not valid hex".to_string(),
+    };
+    let digit = |c: u8| (c as char).to_digit(16).ok_or_else(invalid);
+
+    let hex = hex.as_bytes();
+    if hex.len() & 1 != 0 {
+        return Err(invalid());
+    }
+    hex.chunks(2)
+        .map(|pair| Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+        .collect()
 }
 
 fn emsa_pss_encode(
@@ -72,27 +936,26 @@ fn emsa_pss_encode(
     // See [1], section 9.1.1
     let h_len = hash.output_size();
     let s_len = salt.len();
-    let em_len = (em_bits + 7) / 8;
+    let em_len = sizes::em_len(em_bits);
+    debug_event!(em_len, h_len, s_len, "emsa_pss_encode");
 
     // 1. If the length of M is greater than the input limitation for the
     //     hash function (2^61 - 1 octets for SHA-1), output "message too
     //     long" and stop.
     //
     // 2.  Let mHash = Hash(M), an octet string of length hLen.
-    if m_hash.len() != h_len {
-        return Err(Error::InputNotHashed);
-    }
+    hash::check_len(m_hash, h_len)?;
 
     // 3. If em_len < h_len + s_len + 2, output "encoding error" and stop.
     if em_len < h_len + s_len + 2 {
-        // TODO: Key size too small
-        return Err(Error::Internal);
+        return Err(Error::KeyTooSmall);
     }
 
     let mut em = vec![0; em_len];
 
-    let (db, h) = em.split_at_mut(em_len - h_len - 1);
-    let h = &mut h[..(em_len - 1) - db.len()];
+    // EM = maskedDB || H || 0xbc, DB takes everything before H.
+    let (db, h_and_trailer) = em.split_at_mut(em_len - h_len - 1);
+    let h = &mut h_and_trailer[..h_len];
 
     // 4. Generate a random octet string salt of length s_len; if s_len = 0,
     //     then salt is the empty string.
@@ -124,11 +987,11 @@ fn emsa_pss_encode(
     // 9.  Let dbMask = MGF(H, emLen - hLen - 1).
     //
     // 10. Let maskedDB = DB \xor dbMask.
-    mgf1_xor(db, hash, &h);
+    mgf1_xor(db, hash, h);
 
     // 11. Set the leftmost 8 * em_len - em_bits bits of the leftmost octet in
     //     maskedDB to zero.
-    db[0] &= 0xFF >> (8 * em_len - em_bits);
+    db[0] &= leftmost_octet_mask(em_len, em_bits);
 
     // 12. Let EM = maskedDB || H || 0xbc.
     em[em_len - 1] = 0xBC;
@@ -136,13 +999,25 @@ fn emsa_pss_encode(
     Ok(em)
 }
 
+/// Returns the mask that keeps the low `em_bits - 8 * (em_len - 1)` bits of
+/// the leftmost octet of an encoded message.
+///
+/// When `em_bits` is a multiple of 8 no bits are cleared and the mask is 0xFF.
+fn leftmost_octet_mask(em_len: usize, em_bits: usize) -> u8 {
+    let unused_bits = 8 * em_len - em_bits;
+    debug_assert!(unused_bits < 8);
+    0xFF >> unused_bits
+}
+
+/// Checks the encoded message `em` against `m_hash`, returning the length of
+/// the salt. If `s_len` is `None` the salt length is recovered from `em`.
 fn emsa_pss_verify(
     m_hash: &[u8],
     em: &mut [u8],
     em_bits: usize,
     s_len: Option<usize>,
     hash: &mut dyn DynDigest,
-) -> Result<()> {
+) -> Result<usize> {
     // 1. If the length of M is greater than the input limitation for the
     //    hash function (2^61 - 1 octets for SHA-1), output "inconsistent"
     //    and stop.
@@ -154,65 +1029,113 @@ fn emsa_pss_verify(
     }
 
     // 3. If emLen < hLen + sLen + 2, output "inconsistent" and stop.
-    let em_len = em.len(); //(em_bits + 7) / 8;
-    if em_len < h_len + s_len.unwrap_or_default() + 2 {
-        return Err(Error::Verification);
+    let em_len = em.len();
+    debug_event!(em_len, h_len, "emsa_pss_verify");
+    match s_len.unwrap_or_default().checked_add(h_len + 2) {
+        Some(min_len) if em_len >= min_len => {}
+        _ => return Err(Error::Verification),
     }
 
+    let (db, h) = unmask_db(em, em_bits, hash)?;
+
+    let (s_len, db_valid) = match s_len {
+        // Scan DB for the 0x01 separator after the zero padding. A separator
+        // at DB[0] means the largest salt, em_len - h_len - 2, which is what
+        // `sign` uses by default.
+        None => {
+            let s_len = (0..=em_len - (h_len + 2))
+                .rev()
+                .try_fold(None, |state, i| match (state, db[em_len - h_len - i - 2]) {
+                    (Some(i), _) => Ok(Some(i)),
+                    (_, 1) => Ok(Some(i)),
+                    (_, 0) => Ok(None),
+                    _ => Err(Error::Verification),
+                })?
+                .ok_or(Error::Verification)?;
+            (s_len, Choice::from(1))
+        }
+        Some(s_len) => {
+            // 10. If the emLen - hLen - sLen - 2 leftmost octets of DB are not zero
+            //     or if the octet at position emLen - hLen - sLen - 1 (the leftmost
+            //     position is "position 1") does not have hexadecimal value 0x01,
+            //     output "inconsistent" and stop.
+            //
+            // With the salt length fixed, the layout of DB does not depend on
+            // its contents, so this is decided together with the hash below
+            // without revealing which byte was wrong.
+            let (zeroes, rest) = db.split_at(em_len - h_len - s_len - 2);
+            let zeroes_valid = zeroes
+                .iter()
+                .fold(Choice::from(1), |acc, e| acc & e.ct_eq(&0));
+            (s_len, zeroes_valid & rest[0].ct_eq(&0x01))
+        }
+    };
+
+    // 11. Let salt be the last s_len octets of DB.
+    let salt = &db[db.len() - s_len..];
+
+    if bool::from(db_valid & salted_hash_matches(m_hash, salt, h, hash)) {
+        Ok(s_len)
+    } else {
+        Err(Error::Verification)
+    }
+}
+
+/// Steps 4 to 9 of EMSA-PSS-VERIFY: checks the trailer and the unused bits
+/// of `em` and unmasks DB in place. Returns DB and H. `em` must be at least
+/// `h_len + 2` bytes long.
+fn unmask_db<'a>(
+    em: &'a mut [u8],
+    em_bits: usize,
+    hash: &mut dyn DynDigest,
+) -> Result<(&'a [u8], &'a [u8])> {
+    let h_len = hash.output_size();
+    let em_len = em.len();
+
     // 4. If the rightmost octet of EM does not have hexadecimal value
     //    0xbc, output "inconsistent" and stop.
-    if em[em.len() - 1] != 0xBC {
+    if em[em_len - 1] != 0xBC {
         return Err(Error::Verification);
     }
 
     // 5. Let maskedDB be the leftmost emLen - hLen - 1 octets of EM, and
     //    let H be the next hLen octets.
     let (db, h) = em.split_at_mut(em_len - h_len - 1);
-    let h = &mut h[..h_len];
+    let h = &h[..h_len];
 
     // 6. If the leftmost 8 * em_len - em_bits bits of the leftmost octet in
     //    maskedDB are not all equal to zero, output "inconsistent" and
     //    stop.
-    if db[0] & (0xFF << /*uint*/(8 - (8 * em_len - em_bits))) != 0 {
+    let mask = leftmost_octet_mask(em_len, em_bits);
+    if db[0] & !mask != 0 {
         return Err(Error::Verification);
     }
 
     // 7. Let dbMask = MGF(H, em_len - h_len - 1)
     //
     // 8. Let DB = maskedDB \xor dbMask
-    mgf1_xor(db, hash, &*h);
+    mgf1_xor(db, hash, h);
 
     // 9.  Set the leftmost 8 * emLen - emBits bits of the leftmost octet in DB
     //     to zero.
-    db[0] &= 0xFF >> /*uint*/(8 * em_len - em_bits);
-
-    let s_len = match s_len {
-        None => (0..=em_len - (h_len + 2))
-            .rev()
-            .try_fold(None, |state, i| match (state, db[em_len - h_len - i - 2]) {
-                (Some(i), _) => Ok(Some(i)),
-                (_, 1) => Ok(Some(i)),
-                (_, 0) => Ok(None),
-                _ => Err(Error::Verification),
-            })?
-            .ok_or(Error::Verification)?,
-        Some(s_len) => {
-            // 10. If the emLen - hLen - sLen - 2 leftmost octets of DB are not zero
-            //     or if the octet at position emLen - hLen - sLen - 1 (the leftmost
-            //     position is "position 1") does not have hexadecimal value 0x01,
-            //     output "inconsistent" and stop.
-            let (zeroes, rest) = db.split_at(em_len - h_len - s_len - 2);
-            if zeroes.iter().any(|e| *e != 0x00) || rest[0] != 0x01 {
-                return Err(Error::Verification);
-            }
+    db[0] &= mask;
 
-            s_len
-        }
-    };
+    Ok((db, h))
+}
 
-    // 11. Let salt be the last s_len octets of DB.
-    let salt = &db[db.len() - s_len..];
+/// Steps 12 to 14 of EMSA-PSS-VERIFY: checks that `h` is the hash of the
+/// salted message representative.
+fn check_salted_hash(m_hash: &[u8], salt: &[u8], h: &[u8], hash: &mut dyn DynDigest) -> Result<()> {
+    if salted_hash_matches(m_hash, salt, h, hash).into() {
+        Ok(())
+    } else {
+        Err(Error::Verification)
+    }
+}
 
+/// Steps 12 to 14 of EMSA-PSS-VERIFY like [`check_salted_hash`], returning
+/// the outcome as a `Choice`.
+fn salted_hash_matches(m_hash: &[u8], salt: &[u8], h: &[u8], hash: &mut dyn DynDigest) -> Choice {
     // 12. Let
     //          M' = (0x)00 00 00 00 00 00 00 00 || mHash || salt ;
     //     M' is an octet string of length 8 + hLen + sLen with eight
@@ -227,22 +1150,255 @@ fn emsa_pss_verify(
     let h0 = hash.finalize_reset();
 
     // 14. If H = H', output "consistent." Otherwise, output "inconsistent."
-    if h0.ct_eq(h).into() {
-        Ok(())
-    } else {
-        Err(Error::Verification)
+    h0.ct_eq(h)
+}
+
+/// The `RSASSA-PSS-params` structure of RFC 8017, appendix A.2.3, which
+/// identifies the PSS parameters next to a signature in X.509 and CMS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PssParams {
+    /// Hash function applied to the message.
+    pub hash: Hash,
+    /// Hash function used by MGF1.
+    pub mgf_hash: Hash,
+    /// Length of the salt in bytes.
+    pub salt_len: usize,
+    /// Trailer field, which is always 1 (the `0xbc` trailer byte).
+    pub trailer_field: usize,
+}
+
+impl PssParams {
+    /// Creates the parameters for `hash`, with MGF1 over the same hash and a
+    /// salt of `salt_len` bytes.
+    pub fn new(hash: Hash, salt_len: usize) -> PssParams {
+        PssParams {
+            hash,
+            mgf_hash: hash,
+            salt_len,
+            trailer_field: 1,
+        }
+    }
+
+    /// Encodes the parameters as a DER `RSASSA-PSS-params` sequence.
+    ///
+    /// Fields which hold their default value (SHA-1, MGF1 with SHA-1, a 20
+    /// byte salt and trailer field 1) are omitted, as DER requires.
+    #[cfg(feature = "std")]
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        pss_params::encode(self)
+    }
+
+    /// Parses a DER `RSASSA-PSS-params` sequence.
+    #[cfg(feature = "std")]
+    pub fn from_der(der: &[u8]) -> Result<PssParams> {
+        pss_params::parse(der)
+    }
+}
+
+/// Encodes `RSASSA-PSS-params` for `hash` with MGF1 over the same hash and a
+/// salt of `salt_len` bytes.
+#[cfg(feature = "std")]
+pub fn encode_pss_params(hash: Hash, salt_len: usize) -> Result<Vec<u8>> {
+    PssParams::new(hash, salt_len).to_der()
+}
+
+/// Parses a DER `RSASSA-PSS-params` sequence.
+#[cfg(feature = "std")]
+pub fn parse_pss_params(der: &[u8]) -> Result<PssParams> {
+    PssParams::from_der(der)
+}
+
+#[cfg(feature = "std")]
+mod pss_params {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use num_traits::ToPrimitive;
+    use simple_asn1::{oid, ASN1Block, ASN1Class, BigInt, BigUint, OID};
+
+    use super::PssParams;
+    use crate::errors::{Error, Result};
+    use crate::hash::Hash;
+
+    const DEFAULT_SALT_LEN: usize = 20;
+    const DEFAULT_TRAILER_FIELD: usize = 1;
+
+    fn mgf1_oid() -> OID {
+        oid!(1, 2, 840, 113549, 1, 1, 8)
+    }
+
+    fn parse_error(reason: &str) -> Error {
+        Error::ParseError {
+            reason: format!("invalid RSASSA-PSS-params: {}", reason),
+        }
+    }
+
+    fn hash_algorithm(hash: Hash) -> Result<ASN1Block> {
+        let oid = hash.oid().ok_or_else(|| Error::EncodeError {
+            reason: format!("{:?} has no object identifier", hash),
+        })?;
+
+        Ok(ASN1Block::Sequence(
+            0,
+            vec![ASN1Block::ObjectIdentifier(0, oid), ASN1Block::Null(0)],
+        ))
+    }
+
+    fn explicit(tag: u64, block: ASN1Block) -> ASN1Block {
+        ASN1Block::Explicit(
+            ASN1Class::ContextSpecific,
+            0,
+            BigUint::from(tag),
+            Box::new(block),
+        )
+    }
+
+    pub(super) fn encode(params: &PssParams) -> Result<Vec<u8>> {
+        let mut fields = Vec::new();
+
+        if params.hash != Hash::SHA1 {
+            fields.push(explicit(0, hash_algorithm(params.hash)?));
+        }
+        if params.mgf_hash != Hash::SHA1 {
+            let mgf = ASN1Block::Sequence(
+                0,
+                vec![
+                    ASN1Block::ObjectIdentifier(0, mgf1_oid()),
+                    hash_algorithm(params.mgf_hash)?,
+                ],
+            );
+            fields.push(explicit(1, mgf));
+        }
+        if params.salt_len != DEFAULT_SALT_LEN {
+            fields.push(explicit(
+                2,
+                ASN1Block::Integer(0, BigInt::from(params.salt_len)),
+            ));
+        }
+        if params.trailer_field != DEFAULT_TRAILER_FIELD {
+            fields.push(explicit(
+                3,
+                ASN1Block::Integer(0, BigInt::from(params.trailer_field)),
+            ));
+        }
+
+        simple_asn1::to_der(&ASN1Block::Sequence(0, fields)).map_err(|e| Error::EncodeError {
+            reason: format!("failed to encode RSASSA-PSS-params: {}", e),
+        })
+    }
+
+    fn parse_hash_algorithm(block: &ASN1Block) -> Result<Hash> {
+        match block {
+            ASN1Block::Sequence(_, fields) => match fields.as_slice() {
+                [ASN1Block::ObjectIdentifier(_, oid)]
+                | [ASN1Block::ObjectIdentifier(_, oid), ASN1Block::Null(_)] => {
+                    Hash::from_oid(oid).ok_or_else(|| parse_error("unsupported hash algorithm"))
+                }
+                _ => Err(parse_error("malformed hash algorithm")),
+            },
+            _ => Err(parse_error("expected hash algorithm sequence")),
+        }
+    }
+
+    fn parse_integer(block: &ASN1Block) -> Result<usize> {
+        match block {
+            ASN1Block::Integer(_, value) => value
+                .to_usize()
+                .ok_or_else(|| parse_error("integer out of range")),
+            _ => Err(parse_error("expected integer")),
+        }
+    }
+
+    pub(super) fn parse(der: &[u8]) -> Result<PssParams> {
+        let mut params = PssParams {
+            hash: Hash::SHA1,
+            mgf_hash: Hash::SHA1,
+            salt_len: DEFAULT_SALT_LEN,
+            trailer_field: DEFAULT_TRAILER_FIELD,
+        };
+
+        // simple_asn1 fails to decode empty sequences, which is what all
+        // default parameters encode to.
+        if der == [0x30, 0x00] {
+            return Ok(params);
+        }
+
+        let blocks = simple_asn1::from_der(der)?;
+        let fields = match blocks.as_slice() {
+            [ASN1Block::Sequence(_, fields)] => fields,
+            _ => return Err(parse_error("expected a single sequence")),
+        };
+
+        let mut next_tag = 0;
+        for field in fields {
+            let (tag, value) = match field {
+                ASN1Block::Explicit(ASN1Class::ContextSpecific, _, tag, value) => (
+                    tag.to_u64()
+                        .ok_or_else(|| parse_error("unexpected field"))?,
+                    &**value,
+                ),
+                _ => return Err(parse_error("expected a tagged field")),
+            };
+            // Fields are optional but must appear in order and at most once.
+            if tag < next_tag {
+                return Err(parse_error("fields out of order"));
+            }
+            next_tag = tag + 1;
+
+            match tag {
+                0 => params.hash = parse_hash_algorithm(value)?,
+                1 => {
+                    params.mgf_hash = match value {
+                        ASN1Block::Sequence(_, mgf) => match mgf.as_slice() {
+                            [ASN1Block::ObjectIdentifier(_, oid), hash] if *oid == mgf1_oid() => {
+                                parse_hash_algorithm(hash)?
+                            }
+                            _ => return Err(parse_error("unsupported mask generation function")),
+                        },
+                        _ => return Err(parse_error("expected mask generation function sequence")),
+                    }
+                }
+                2 => params.salt_len = parse_integer(value)?,
+                3 => params.trailer_field = parse_integer(value)?,
+                _ => return Err(parse_error("unexpected field")),
+            }
+        }
+
+        Ok(params)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{PaddingScheme, PublicKey, RSAPrivateKey, RSAPublicKey};
+    use super::{
+        emsa_pss_encode, emsa_pss_verify, encode_pss_params, leftmost_octet_mask,
+        min_key_bits_for_pss, parse_pss_params, sign, sign_deterministic_pss,
+        sign_deterministic_salt, sign_digest, sign_prehashed, sign_pss_b64url,
+        sign_pss_hash_len_salt, sign_pss_hex, sign_pss_with_salt, sign_pss_with_salt_out,
+        sign_with_exponentiation, unmask_db, verify, verify_digest, verify_int, verify_message,
+        verify_pss_b64url, verify_pss_compat, verify_pss_dyn, verify_pss_ex, verify_pss_full,
+        verify_pss_hash_len_salt, verify_pss_hex, verify_pss_min_bits, verify_pss_with_params,
+        verify_with_scratch, PssParams, PssVerifier, SaltPolicy, SaltReuse, SaltReuseDetector,
+    };
+    use crate::algorithms::mgf1_xor;
+    use crate::raw::{DecryptionPrimitive, EncryptionPrimitive};
+    use crate::hash::Hash;
+    use crate::errors::Error;
+    use crate::policy::WeakKeyReason;
+    use crate::sizes;
+    use crate::test_util;
+    use crate::{
+        PaddingScheme, PrivateExponentiation, PublicKey, PublicKeyParts, RSAPrivateKey,
+        RSAPublicKey,
+    };
 
     use num_bigint::BigUint;
     use num_traits::{FromPrimitive, Num};
     use sha1::{Digest, Sha1};
+    use sha2::{Sha256, Sha512};
     use std::time::SystemTime;
-    use rand::{SeedableRng, rngs::StdRng};
+    use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
 
     fn get_private_key() -> RSAPrivateKey {
         // In order to generate new test vectors you'll need the PEM form of this key:
@@ -320,4 +1476,1272 @@ mod test {
                 .expect("failed to verify");
         }
     }
+
+    #[test]
+    fn test_leftmost_octet_mask() {
+        // em_len rounds em_bits up to whole bytes, so at most 7 bits are
+        // unused and a multiple of 8 masks nothing.
+        for em_bits in 1..=64 {
+            let em_len = sizes::em_len(em_bits);
+            let kept = em_bits - 8 * (em_len - 1);
+            let expected = ((1u16 << kept) - 1) as u8;
+            assert_eq!(
+                leftmost_octet_mask(em_len, em_bits),
+                expected,
+                "em_bits {}",
+                em_bits
+            );
+        }
+
+        // A 2041-bit modulus has em_bits == 2040, a 2040-bit one clears the
+        // top bit of the encoded message.
+        for &(n_bits, mask) in &[(2041, 0xFF), (2040, 0x7F), (2048, 0x7F), (2047, 0x3F)] {
+            let em_bits = sizes::em_bits(n_bits).unwrap();
+            assert_eq!(leftmost_octet_mask(sizes::em_len(em_bits), em_bits), mask);
+        }
+    }
+
+    #[test]
+    fn test_emsa_pss_encode_all_salt_lengths() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let m_hash = Sha256::digest(b"every salt length");
+        let h_len = 32;
+
+        // Both a whole number of octets and one spare bit in the leftmost one.
+        for &em_bits in [1023, 1024, 8 * (h_len + 2) + 1].iter() {
+            let em_len = sizes::em_len(em_bits);
+            for s_len in 0..=em_len - h_len - 2 {
+                let mut salt = vec![0u8; s_len];
+                rng.fill(&mut salt[..]);
+                let mut em = emsa_pss_encode(&m_hash, em_bits, &salt, &mut Sha256::new()).unwrap();
+                assert_eq!(em.len(), em_len);
+                assert_eq!(em[em_len - 1], 0xbc);
+
+                let mut unmasked = em.clone();
+                let (db, h) = unmask_db(&mut unmasked, em_bits, &mut Sha256::new()).unwrap();
+                assert_eq!(db.len(), em_len - h_len - 1);
+                assert_eq!(h.len(), h_len);
+                let (ps, rest) = db.split_at(em_len - s_len - h_len - 2);
+                assert!(ps.iter().all(|&b| b == 0));
+                assert_eq!(rest, &[&[0x01][..], &salt].concat()[..]);
+
+                let mut copy = em.clone();
+                let recovered =
+                    emsa_pss_verify(&m_hash, &mut copy, em_bits, None, &mut Sha256::new());
+                assert_eq!(recovered.unwrap(), s_len);
+                emsa_pss_verify(&m_hash, &mut em, em_bits, Some(s_len), &mut Sha256::new())
+                    .unwrap();
+            }
+            let salt = vec![0u8; em_len - h_len - 1];
+            match emsa_pss_encode(&m_hash, em_bits, &salt, &mut Sha256::new()) {
+                Err(Error::KeyTooSmall) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_byte_boundary_modulus() {
+        // A 1025-bit modulus gives em_bits == 1024, so no bits of the
+        // leftmost octet are masked off and em_len is one byte shorter than
+        // the signature.
+        let mut rng = StdRng::seed_from_u64(1025);
+        let priv_key = RSAPrivateKey::new(&mut rng, 1025).expect("failed to generate key");
+        assert_eq!(priv_key.n().bits(), 1025);
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+
+        for msg in &["", "test\n", "hello world"] {
+            let digest = Sha256::digest(msg.as_bytes()).to_vec();
+            for salt_len in &[None, Some(0), Some(32)] {
+                let padding = match salt_len {
+                    None => PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                    Some(len) => PaddingScheme::new_pss_with_salt::<Sha256, _>(rng.clone(), *len),
+                };
+                let sig = priv_key
+                    .sign_blinded(&mut rng, padding, &digest)
+                    .expect("failed to sign");
+                assert_eq!(sig.len(), 129);
+
+                pub_key
+                    .verify(
+                        PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                        &digest,
+                        &sig,
+                    )
+                    .expect("failed to verify");
+            }
+        }
+
+        // n - 1 is its own signature representative and does not fit in em_len.
+        let digest = Sha256::digest(b"test").to_vec();
+        let sig = (pub_key.n() - 1u32).to_bytes_be();
+        assert!(pub_key
+            .verify(
+                PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                &digest,
+                &sig
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_degenerate_key_sizes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let digest = Sha1::digest(b"test").to_vec();
+
+        for n in &[0u32, 15] {
+            let priv_key = RSAPrivateKey::from_components(
+                BigUint::from_u32(*n).unwrap(),
+                BigUint::from_u64(65537).unwrap(),
+                BigUint::from_u64(1).unwrap(),
+                vec![BigUint::from_u64(3).unwrap(), BigUint::from_u64(5).unwrap()],
+            );
+
+            for salt_len in &[None, Some(0)] {
+                let padding = match salt_len {
+                    None => PaddingScheme::new_pss::<Sha1, _>(rng.clone()),
+                    Some(len) => PaddingScheme::new_pss_with_salt::<Sha1, _>(rng.clone(), *len),
+                };
+                match priv_key.sign_blinded(&mut rng, padding, &digest) {
+                    Err(Error::KeyTooSmall) => {}
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+
+            let pub_key = RSAPublicKey::new(
+                BigUint::from_u32(*n).unwrap(),
+                BigUint::from_u64(65537).unwrap(),
+            )
+            .unwrap();
+            let sig = vec![0u8; pub_key.size()];
+            assert!(pub_key
+                .verify(
+                    PaddingScheme::new_pss::<Sha1, _>(rng.clone()),
+                    &digest,
+                    &sig
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_sign_deterministic_pss() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let digest = Sha256::digest(b"test\n").to_vec();
+
+        // Matches `openssl pkeyutl -sign -pkeyopt rsa_padding_mode:pss
+        // -pkeyopt rsa_pss_saltlen:0 -pkeyopt digest:sha256` with this key.
+        let expected = "6c112b2a12a9d1eed7edbea2ae7b07a6aaa94b19606058b920bef56a2236a3898b53c9baf749dee93d86edab32b37e0dacc6b268f7630d657690772117ccb81c";
+
+        let sig = sign_deterministic_pss::<Sha256, _>(&priv_key, &digest).unwrap();
+        assert_eq!(hex::encode(&sig), expected);
+        assert_eq!(
+            sign_deterministic_pss::<Sha256, _>(&priv_key, &digest).unwrap(),
+            sig
+        );
+
+        verify(&pub_key, &digest, &sig, &mut Sha256::new()).expect("failed to verify");
+        let s_len = verify_pss_ex::<_, Sha256>(&pub_key, &digest, &sig, SaltPolicy::Exactly(0))
+            .expect("failed to verify");
+        assert_eq!(s_len, 0);
+    }
+
+    #[test]
+    fn test_sign_deterministic_salt() {
+        use hmac::{Hmac, Mac, NewMac};
+
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let mut rng = StdRng::seed_from_u64(0);
+        let digest = Sha1::digest(b"test\n").to_vec();
+
+        let sig = sign_deterministic_salt::<Sha1, StdRng>(None, &priv_key, &digest).unwrap();
+        assert_eq!(
+            sign_deterministic_salt::<Sha1, _>(Some(&mut rng), &priv_key, &digest).unwrap(),
+            sig
+        );
+        // A 512-bit key is too small for SHA-256 with a 32-byte salt.
+        match sign_deterministic_salt::<Sha256, StdRng>(None, &priv_key, &Sha256::digest(b"")) {
+            Err(Error::KeyTooSmall) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let s_len = verify_pss_ex::<_, Sha1>(&pub_key, &digest, &sig, SaltPolicy::Exactly(20))
+            .expect("failed to verify");
+        assert_eq!(s_len, 20);
+        verify(&pub_key, &digest, &sig, &mut Sha1::new()).expect("failed to verify");
+
+        // Recompute the salt following the documented derivation.
+        let d = priv_key.d().to_bytes_be();
+        assert_eq!(d.len(), priv_key.size());
+        let mut mac = Hmac::<Sha1>::new_varkey(&Sha1::digest(&d)).unwrap();
+        mac.update(b"RSASSA-PSS deterministic salt");
+        mac.update(&digest);
+        let salt = mac.finalize().into_bytes();
+        let expected =
+            sign_pss_with_salt::<StdRng, _>(None, &priv_key, &digest, &salt, &mut Sha1::new())
+                .unwrap();
+        assert_eq!(sig, expected);
+
+        let other_digest = Sha1::digest(b"test").to_vec();
+        let other =
+            sign_deterministic_salt::<Sha1, StdRng>(None, &priv_key, &other_digest).unwrap();
+        assert_ne!(other, sig);
+        verify(&pub_key, &other_digest, &other, &mut Sha1::new()).expect("failed to verify");
+    }
+
+    #[test]
+    fn test_max_salt_len_is_auto_detected() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Include moduli whose bit length is one more than a multiple of 8,
+        // where the encoded message is a byte shorter than the modulus.
+        for &bits in &[512, 513, 519, 520, 521, 527, 528, 529, 767, 1025] {
+            let priv_key = RSAPrivateKey::new(&mut rng, bits).unwrap();
+            let pub_key: RSAPublicKey = priv_key.clone().into();
+            let em_len = (bits - 1).div_ceil(8);
+
+            for _ in 0..4 {
+                let mut msg = vec![0u8; rng.gen_range(0, 64)];
+                rng.fill(&mut msg[..]);
+
+                let hashed = Sha1::digest(&msg).to_vec();
+                let sig = sign::<_, StdRng, _>(
+                    &mut rng,
+                    None,
+                    &priv_key,
+                    &hashed,
+                    None,
+                    &mut Sha1::new(),
+                )
+                .unwrap();
+                let s_len =
+                    verify_pss_ex::<_, Sha1>(&pub_key, &hashed, &sig, SaltPolicy::Any).unwrap();
+                assert_eq!(s_len, em_len - 20 - 2, "{} bits", bits);
+
+                let hashed = Sha256::digest(&msg).to_vec();
+                let result = sign::<_, StdRng, _>(
+                    &mut rng,
+                    None,
+                    &priv_key,
+                    &hashed,
+                    None,
+                    &mut Sha256::new(),
+                );
+                if em_len < 32 + 2 {
+                    assert!(result.is_err());
+                    continue;
+                }
+                let sig = result.unwrap();
+                let s_len =
+                    verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Any).unwrap();
+                assert_eq!(s_len, em_len - 32 - 2, "{} bits", bits);
+
+                // Any explicit salt length comes back unchanged as well.
+                let salt_len = rng.gen_range(0, em_len - 32 - 2 + 1);
+                let sig = sign::<_, StdRng, _>(
+                    &mut rng,
+                    None,
+                    &priv_key,
+                    &hashed,
+                    Some(salt_len),
+                    &mut Sha256::new(),
+                )
+                .unwrap();
+                let s_len =
+                    verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Any).unwrap();
+                assert_eq!(s_len, salt_len, "{} bits", bits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_prehashed() {
+        let priv_key = get_private_key();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Stands in for a hash computed by an external provider.
+        let hashed = Sha256::digest(b"test\n").to_vec();
+
+        let sig = sign_prehashed::<_, StdRng, _>(
+            &mut rng,
+            None,
+            &priv_key,
+            &hashed,
+            32,
+            None,
+            &mut Sha256::new(),
+        )
+        .expect("failed to sign");
+        priv_key
+            .verify(
+                PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                &hashed,
+                &sig,
+            )
+            .expect("failed to verify");
+
+        assert!(sign_prehashed::<_, StdRng, _>(
+            &mut rng,
+            None,
+            &priv_key,
+            &hashed,
+            20,
+            None,
+            &mut Sha256::new(),
+        )
+        .is_err());
+        assert!(sign_prehashed::<_, StdRng, _>(
+            &mut rng,
+            None,
+            &priv_key,
+            &hashed,
+            32,
+            None,
+            &mut Sha1::new(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_with_scratch() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scratch = Vec::new();
+
+        // 1025 bits makes em_len one byte shorter than the signature.
+        for &bits in &[512, 520, 1025] {
+            let priv_key = RSAPrivateKey::new(&mut rng, bits).unwrap();
+            let pub_key = priv_key.to_public_key();
+
+            for msg in &["", "test\n", "hello world"] {
+                let digest = Sha256::digest(msg.as_bytes()).to_vec();
+                let padding = PaddingScheme::new_pss::<Sha256, _>(rng.clone());
+                let sig = priv_key.sign_blinded(&mut rng, padding, &digest).unwrap();
+                verify_with_scratch::<_, Sha256>(&pub_key, &digest, &sig, &mut scratch).unwrap();
+
+                let mut tampered = sig.clone();
+                tampered[sig.len() / 2] ^= 1;
+                let too_large = (pub_key.n() - 1u32).to_bytes_be();
+                for sig in &[sig, tampered, too_large, vec![0; pub_key.size()]] {
+                    let expected = verify(&pub_key, &digest, sig, &mut Sha256::new()).is_ok();
+                    let result =
+                        verify_with_scratch::<_, Sha256>(&pub_key, &digest, sig, &mut scratch);
+                    assert_eq!(result.is_ok(), expected);
+                }
+                assert!(
+                    verify_with_scratch::<_, Sha256>(&pub_key, &digest, &[], &mut scratch).is_err()
+                );
+            }
+        }
+    }
+
+    /// Signs `hashed` with the legacy `DB = salt || 0x01 || PS` layout.
+    fn sign_legacy_layout(priv_key: &RSAPrivateKey, hashed: &[u8], salt: &[u8]) -> Vec<u8> {
+        let em_bits = priv_key.n().bits() - 1;
+        let em_len = sizes::em_len(em_bits);
+        let h_len = hashed.len();
+
+        let mut hasher = Sha1::new();
+        hasher.update([0u8; 8]);
+        hasher.update(hashed);
+        hasher.update(salt);
+        let h = hasher.finalize();
+
+        let mut em = vec![0u8; em_len];
+        let db_len = em_len - h_len - 1;
+        em[..salt.len()].copy_from_slice(salt);
+        em[salt.len()] = 0x01;
+        mgf1_xor(&mut em[..db_len], &mut Sha1::new(), &h);
+        em[0] &= 0xFF >> (8 * em_len - em_bits);
+        em[db_len..em_len - 1].copy_from_slice(&h);
+        em[em_len - 1] = 0xBC;
+
+        priv_key
+            .raw_decryption_primitive::<StdRng>(None, &em, priv_key.size())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_verify_pss_compat() {
+        let priv_key = get_private_key();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha1::digest(b"test\n").to_vec();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Keep the top bit of the salt clear, the legacy signer masks it off.
+        for salt in &[vec![], vec![0x01; 20], vec![0x7f, 0x00, 0x01, 0x00]] {
+            let sig = sign_legacy_layout(&priv_key, &hashed, salt);
+            verify_pss_compat::<_, Sha1>(&pub_key, &hashed, &sig).unwrap();
+            assert!(verify(&pub_key, &hashed, &sig, &mut Sha1::new()).is_err());
+
+            let other = Sha1::digest(b"other message").to_vec();
+            assert!(verify_pss_compat::<_, Sha1>(&pub_key, &other, &sig).is_err());
+        }
+
+        // Standard signatures still verify.
+        let sig = sign(
+            &mut rng,
+            None::<&mut StdRng>,
+            &priv_key,
+            &hashed,
+            None,
+            &mut Sha1::new(),
+        )
+        .unwrap();
+        verify_pss_compat::<_, Sha1>(&pub_key, &hashed, &sig).unwrap();
+
+        // Tampered legacy signatures are rejected.
+        let mut sig = sign_legacy_layout(&priv_key, &hashed, &[0x05; 8]);
+        sig[10] ^= 1;
+        assert!(verify_pss_compat::<_, Sha1>(&pub_key, &hashed, &sig).is_err());
+    }
+
+    #[test]
+    fn test_signature_length_mismatch() {
+        let priv_key = get_private_key();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha1::digest(b"test\n").to_vec();
+        let sig = sign_deterministic_pss::<Sha1, _>(&priv_key, &hashed).unwrap();
+
+        for len in &[0, sig.len() - 1, sig.len() + 1] {
+            let mut wrong = sig.clone();
+            wrong.resize(*len, 0);
+            match verify(&pub_key, &hashed, &wrong, &mut Sha1::new()) {
+                Err(Error::SignatureLength { expected: 64, got }) if got == *len => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match verify_with_scratch::<_, Sha1>(&pub_key, &hashed, &wrong, &mut Vec::new()) {
+                Err(Error::SignatureLength { .. }) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        // A signature of the right length that does not verify stays uniform.
+        let mut tampered = sig;
+        tampered[0] ^= 1;
+        match verify(&pub_key, &hashed, &tampered, &mut Sha1::new()) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_pss_ex() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let mut rng = StdRng::seed_from_u64(0);
+        let digest = Sha1::digest(b"test\n").to_vec();
+
+        // A 512-bit key with SHA-1 fits salts of up to 42 bytes.
+        for s_len in &[0, 1, 20, 42] {
+            let padding = PaddingScheme::new_pss_with_salt::<Sha1, _>(rng.clone(), *s_len);
+            let sig = priv_key
+                .sign_blinded(&mut rng, padding, &digest)
+                .expect("failed to sign");
+
+            let verify = |policy| verify_pss_ex::<_, Sha1>(&pub_key, &digest, &sig, policy);
+            assert_eq!(verify(SaltPolicy::Any).unwrap(), *s_len);
+            assert_eq!(verify(SaltPolicy::Exactly(*s_len)).unwrap(), *s_len);
+            assert!(verify(SaltPolicy::Exactly(s_len + 1)).is_err());
+            // Must not overflow the length checks.
+            assert!(verify(SaltPolicy::Exactly(!0)).is_err());
+            if *s_len > 0 {
+                assert!(verify(SaltPolicy::Exactly(s_len - 1)).is_err());
+            }
+            assert_eq!(verify(SaltPolicy::AtLeast(0)).unwrap(), *s_len);
+            assert_eq!(verify(SaltPolicy::AtLeast(*s_len)).unwrap(), *s_len);
+            assert!(verify(SaltPolicy::AtLeast(s_len + 1)).is_err());
+
+            let mut bad_digest = digest.clone();
+            bad_digest[0] ^= 1;
+            assert!(
+                verify_pss_ex::<_, Sha1>(&pub_key, &bad_digest, &sig, SaltPolicy::Any).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_key_bits_for_pss() {
+        assert_eq!(min_key_bits_for_pss::<Sha256>(32), 522);
+        assert_eq!(min_key_bits_for_pss::<Sha256>(0), 266);
+        assert_eq!(min_key_bits_for_pss::<Sha1>(20), 330);
+        assert_eq!(min_key_bits_for_pss::<Sha512>(64), 1034);
+
+        // The shortest key signs, one bit less is too small.
+        let mut rng = StdRng::seed_from_u64(0);
+        let hashed = Sha256::digest(b"testing");
+        let salt = [0x5a; 32];
+        let bits = min_key_bits_for_pss::<Sha256>(salt.len());
+        let priv_key = RSAPrivateKey::new(&mut rng, bits).unwrap();
+        let sig = sign_pss_with_salt(
+            Some(&mut rng),
+            &priv_key,
+            &hashed,
+            &salt,
+            &mut Sha256::new(),
+        )
+        .unwrap();
+        verify(&priv_key.to_public_key(), &hashed, &sig, &mut Sha256::new())
+            .expect("failed to verify");
+
+        let priv_key = RSAPrivateKey::new(&mut rng, bits - 1).unwrap();
+        match sign_pss_with_salt(
+            Some(&mut rng),
+            &priv_key,
+            &hashed,
+            &salt,
+            &mut Sha256::new(),
+        ) {
+            Err(Error::KeyTooSmall) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sign_verify_pss_hash_len_salt() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_2048();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"testing");
+
+        let sig = sign_pss_hash_len_salt::<Sha256, _, _>(priv_key, &mut rng, &hashed).unwrap();
+        verify_pss_hash_len_salt::<_, Sha256>(&pub_key, &hashed, &sig).expect("failed to verify");
+        let s_len = verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Any);
+        assert_eq!(s_len.unwrap(), 32);
+
+        // The default of `sign` is the longest salt that fits, which is fine
+        // for PSS but not for JOSE or TLS 1.3.
+        for &salt_len in [None, Some(0), Some(20)].iter() {
+            let sig = sign::<_, StdRng, _>(
+                &mut rng,
+                None,
+                priv_key,
+                &hashed,
+                salt_len,
+                &mut Sha256::new(),
+            )
+            .unwrap();
+            verify(&pub_key, &hashed, &sig, &mut Sha256::new()).unwrap();
+            match verify_pss_hash_len_salt::<_, Sha256>(&pub_key, &hashed, &sig) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_pss_with_params() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"testing");
+        let (sig, _) =
+            sign_pss_with_salt_out::<Sha256, _, _>(priv_key, &mut rng, &hashed, 32).unwrap();
+
+        let der = encode_pss_params(Hash::SHA2_256, 32).unwrap();
+        let params = PssParams::from_der(&der).unwrap();
+        verify_pss_with_params::<_, Sha256>(&pub_key, Hash::SHA2_256, &hashed, &sig, &params)
+            .expect("failed to verify");
+
+        // The salt length is enforced, not recovered.
+        let params_20 = PssParams::new(Hash::SHA2_256, 20);
+        let sha256 = Hash::SHA2_256;
+        match verify_pss_with_params::<_, Sha256>(&pub_key, sha256, &hashed, &sig, &params_20) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let unsupported = [
+            PssParams::new(Hash::SHA2_512, 32),
+            // As long as SHA-256, but not the hash `H` stands for.
+            PssParams::new(Hash::SHA3_256, 32),
+            PssParams {
+                mgf_hash: Hash::SHA1,
+                ..params
+            },
+            PssParams {
+                trailer_field: 2,
+                ..params
+            },
+        ];
+        for params in unsupported.iter() {
+            match verify_pss_with_params::<_, Sha256>(&pub_key, sha256, &hashed, &sig, params) {
+                Err(Error::InvalidPaddingScheme) => {}
+                other => panic!("unexpected result for {:?}: {:?}", params, other),
+            }
+        }
+
+        // `hash` has to match `H` as well.
+        let params_512 = PssParams::new(Hash::SHA2_512, 32);
+        let sha512 = Hash::SHA2_512;
+        match verify_pss_with_params::<_, Sha256>(&pub_key, sha512, &hashed, &sig, &params_512) {
+            Err(Error::InvalidPaddingScheme) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    /// Stands in for an HSM that only exposes the raw private key operation.
+    struct MockHsm {
+        key: RSAPrivateKey,
+        calls: core::cell::Cell<usize>,
+        faulty: bool,
+    }
+
+    impl PublicKeyParts for MockHsm {
+        fn n(&self) -> &BigUint {
+            self.key.n()
+        }
+
+        fn e(&self) -> &BigUint {
+            self.key.e()
+        }
+    }
+
+    impl PrivateExponentiation for MockHsm {
+        fn modpow_private(
+            &self,
+            rng: Option<&mut dyn RngCore>,
+            base: &BigUint,
+        ) -> crate::errors::Result<BigUint> {
+            assert!(rng.is_some());
+            self.calls.set(self.calls.get() + 1);
+            let s = base.modpow(self.key.d(), self.key.n());
+            Ok(if self.faulty { s + 1u32 } else { s })
+        }
+    }
+
+    #[test]
+    fn test_sign_with_exponentiation() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let hsm = MockHsm {
+            key: test_util::test_key_1024().clone(),
+            calls: core::cell::Cell::new(0),
+            faulty: false,
+        };
+        let pub_key = hsm.key.to_public_key();
+        let hashed = Sha256::digest(b"testing");
+
+        let sig = sign_with_exponentiation(&mut rng, &hsm, &hashed, Some(32), &mut Sha256::new())
+            .unwrap();
+        assert_eq!(hsm.calls.get(), 1);
+        let salt_len = verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Any);
+        assert_eq!(salt_len.unwrap(), 32);
+
+        // With the same salt the software key gives the same signature.
+        let mut salt = [0u8; 32];
+        StdRng::seed_from_u64(5).fill(&mut salt[..]);
+        let expected =
+            sign_pss_with_salt::<StdRng, _>(None, &hsm.key, &hashed, &salt, &mut Sha256::new())
+                .unwrap();
+        let from_hsm = sign_with_exponentiation(
+            &mut StdRng::seed_from_u64(5),
+            &hsm,
+            &hashed,
+            Some(32),
+            &mut Sha256::new(),
+        );
+        assert_eq!(from_hsm.unwrap(), expected);
+        let from_key = sign_with_exponentiation(
+            &mut StdRng::seed_from_u64(5),
+            &hsm.key,
+            &hashed,
+            Some(32),
+            &mut Sha256::new(),
+        );
+        assert_eq!(from_key.unwrap(), expected);
+
+        // A wrong result is caught before it is handed out.
+        let faulty = MockHsm {
+            faulty: true,
+            ..hsm
+        };
+        match sign_with_exponentiation(&mut rng, &faulty, &hashed, None, &mut Sha256::new()) {
+            Err(Error::Internal) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_pss_min_bits() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024().clone();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let digest = Sha256::digest(b"test\n").to_vec();
+        let padding = PaddingScheme::new_pss::<Sha256, _>(rng.clone());
+        let sig = priv_key.sign_blinded(&mut rng, padding, &digest).unwrap();
+
+        verify_pss_min_bits::<_, Sha256>(&pub_key, &digest, &sig, 1024).unwrap();
+        match verify_pss_min_bits::<_, Sha256>(&pub_key, &digest, &sig, 2048) {
+            Err(Error::WeakKey {
+                reason:
+                    WeakKeyReason::TooSmall {
+                        bits: 1024,
+                        min_bits: 2048,
+                    },
+            }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let mut bad_digest = digest.clone();
+        bad_digest[0] ^= 1;
+        match verify_pss_min_bits::<_, Sha256>(&pub_key, &bad_digest, &sig, 1024) {
+            Err(Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pss_params() {
+        // Taken from the signature algorithm of certificates created by
+        // `openssl req -x509 -sha256 -sigopt rsa_padding_mode:pss -sigopt rsa_pss_saltlen:32`,
+        // and with `-sha384 -sigopt rsa_pss_saltlen:20 -sigopt rsa_mgf1_md:sha1`.
+        let sha256 = hex::decode("3034a00f300d06096086480165030402010500a11c301a06092a864886f70d010108300d06096086480165030402010500a203020120").unwrap();
+        let sha384 = hex::decode("3011a00f300d06096086480165030402020500").unwrap();
+
+        let params = PssParams::new(Hash::SHA2_256, 32);
+        assert_eq!(encode_pss_params(Hash::SHA2_256, 32).unwrap(), sha256);
+        assert_eq!(parse_pss_params(&sha256).unwrap(), params);
+
+        let params = PssParams {
+            mgf_hash: Hash::SHA1,
+            ..PssParams::new(Hash::SHA2_384, 20)
+        };
+        assert_eq!(params.to_der().unwrap(), sha384);
+        assert_eq!(PssParams::from_der(&sha384).unwrap(), params);
+
+        // All defaults: an empty sequence.
+        let params = PssParams::new(Hash::SHA1, 20);
+        assert_eq!(params.to_der().unwrap(), vec![0x30, 0x00]);
+        assert_eq!(PssParams::from_der(&[0x30, 0x00]).unwrap(), params);
+
+        for hash in &[
+            Hash::SHA2_224,
+            Hash::SHA2_512,
+            Hash::SHA3_256,
+            Hash::SHA3_512,
+        ] {
+            let params = PssParams {
+                trailer_field: 2,
+                ..PssParams::new(*hash, 0)
+            };
+            assert_eq!(
+                PssParams::from_der(&params.to_der().unwrap()).unwrap(),
+                params
+            );
+        }
+
+        assert!(encode_pss_params(Hash::MD5SHA1, 32).is_err());
+        // Fields out of order.
+        let out_of_order = hex::decode("3016a203020120a00f300d06096086480165030402010500").unwrap();
+        assert!(parse_pss_params(&out_of_order).is_err());
+        assert!(parse_pss_params(&hex::decode("3003020120").unwrap()).is_err());
+        assert!(parse_pss_params(&[0x30]).is_err());
+    }
+
+    #[test]
+    fn test_streaming_verify() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut msg = vec![0u8; 4 * 1024 * 1024 + 17];
+        rng.fill(&mut msg[..]);
+
+        let digest = Sha256::digest(&msg).to_vec();
+        let sig = priv_key
+            .sign_blinded(
+                &mut rng.clone(),
+                PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                &digest,
+            )
+            .expect("failed to sign");
+
+        let mut verifier = PssVerifier::<_, Sha256>::new(&pub_key);
+        for chunk in msg.chunks(64 * 1024) {
+            verifier.update(chunk);
+        }
+        verifier.verify(&sig).expect("failed to verify");
+
+        let mut verifier = PssVerifier::<_, Sha256>::new(&pub_key);
+        for chunk in msg[..msg.len() - 1].chunks(64 * 1024) {
+            verifier.update(chunk);
+        }
+        verifier.update(&[msg[msg.len() - 1] ^ 1]);
+        assert!(verifier.verify(&sig).is_err());
+    }
+
+    #[test]
+    fn test_sign_rejects_oversized_salt_before_allocating() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let hashed = Sha256::digest(b"salt");
+        // 34 bytes of encoded message hold a SHA-256 hash and no salt at all,
+        // 33 do not even hold that, so the default salt length would underflow.
+        for &(bits, fits_hash) in &[(8 * 34 + 1, true), (8 * 33 + 1, false)] {
+            let priv_key = RSAPrivateKey::new(&mut rng, bits).unwrap();
+            let mut sign_with = |salt_len| {
+                let digest = &mut Sha256::new();
+                sign(
+                    &mut rng,
+                    None::<&mut StdRng>,
+                    &priv_key,
+                    &hashed,
+                    salt_len,
+                    digest,
+                )
+            };
+            assert_eq!(sign_with(None).is_ok(), fits_hash);
+            for &salt_len in &[1, 1 << 40, !0] {
+                match sign_with(Some(salt_len)) {
+                    Err(Error::KeyTooSmall) => {}
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_emsa_pss_verify_fixed_salt_rejects_malformed_db() {
+        let m_hash = Sha256::digest(b"malformed");
+        let (em_bits, h_len, s_len) = (1024, 32, 20);
+        let em_len = em_bits / 8;
+        let db_len = em_len - h_len - 1;
+        let em = emsa_pss_encode(&m_hash, em_bits, &[0x5a; 20], &mut Sha256::new()).unwrap();
+
+        // Changes one byte of the unmasked DB and masks it again.
+        let with_db_byte = |pos: usize, value: u8| {
+            let mut em = em.clone();
+            let (db, h) = em.split_at_mut(db_len);
+            mgf1_xor(db, &mut Sha256::new(), &h[..h_len]);
+            db[pos] = value;
+            mgf1_xor(db, &mut Sha256::new(), &h[..h_len]);
+            em
+        };
+
+        let separator = db_len - s_len - 1;
+        // Nonzero padding, a wrong separator and a changed salt.
+        let cases = [
+            (0, 1),
+            (separator / 2, 0x80),
+            (separator - 1, 1),
+            (separator, 0),
+            (separator, 2),
+            (db_len - 1, 0),
+        ];
+        for &(pos, value) in cases.iter() {
+            let mut em = with_db_byte(pos, value);
+            match emsa_pss_verify(&m_hash, &mut em, em_bits, Some(s_len), &mut Sha256::new()) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result for DB[{}] = {}: {:?}", pos, value, other),
+            }
+        }
+
+        // The unmodified DB still verifies, as does rewriting a byte with
+        // the value it already has.
+        let mut em = with_db_byte(separator, 1);
+        emsa_pss_verify(&m_hash, &mut em, em_bits, Some(s_len), &mut Sha256::new()).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "timing-tests")]
+    fn test_emsa_pss_verify_salted_hash_timing() {
+        let m_hash = Sha256::digest(b"timing");
+        let mut other_hash = m_hash;
+        other_hash[0] ^= 1;
+        let em = emsa_pss_encode(&m_hash, 1024, &[0x5a; 32], &mut Sha256::new()).unwrap();
+
+        // Only the final comparison of H and H' sees the difference, the
+        // mismatching H' differs from H in its first byte almost surely.
+        let verify = |m_hash: &[u8]| {
+            let mut em = em.clone();
+            emsa_pss_verify(m_hash, &mut em, 1024, Some(32), &mut Sha256::new()).is_ok()
+        };
+        assert!(verify(&m_hash) && !verify(&other_hash));
+        test_util::assert_timing_indistinguishable(
+            20_000,
+            10.0,
+            || verify(&m_hash),
+            || verify(&other_hash),
+        );
+    }
+
+    #[test]
+    fn test_verify_pss_dyn() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let mut rng = StdRng::seed_from_u64(0);
+        let hashed = Sha256::digest(b"runtime");
+        let sig = sign(
+            &mut rng,
+            None::<&mut StdRng>,
+            &priv_key,
+            &hashed,
+            None,
+            &mut Sha256::new(),
+        )
+        .unwrap();
+
+        // As from the `alg` of an untrusted header.
+        let hash_for = |alg: &str| match alg {
+            "PS256" => Hash::SHA2_256,
+            "PS384" => Hash::SHA2_384,
+            _ => Hash::SHA2_512,
+        };
+        let mut digest = hash_for("PS256").new_dyn_digest().unwrap();
+        verify_pss_dyn(&pub_key, &mut *digest, &hashed, &sig).unwrap();
+        // The digest is reset after use and can verify again.
+        verify_pss_dyn(&pub_key, &mut *digest, &hashed, &sig).unwrap();
+
+        let other = Sha256::digest(b"other");
+        assert!(verify_pss_dyn(&pub_key, &mut Sha256::new(), &other, &sig).is_err());
+        match verify_pss_dyn(&pub_key, &mut Sha1::new(), &hashed, &sig) {
+            Err(Error::DigestLength {
+                expected: 20,
+                got: 32,
+            }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut digest = hash_for("PS384").new_dyn_digest().unwrap();
+        match verify_pss_dyn(&pub_key, &mut *digest, &hashed, &sig) {
+            Err(Error::DigestLength {
+                expected: 48,
+                got: 32,
+            }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match Hash::SHA3_256.new_dyn_digest() {
+            Err(Error::InvalidPaddingScheme) => {}
+            Err(err) => panic!("unexpected error: {:?}", err),
+            Ok(_) => panic!("unexpected digest"),
+        }
+    }
+
+    #[test]
+    fn test_verify_int() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for &bits in &[512, 513, 519] {
+            let priv_key = RSAPrivateKey::new(&mut rng, bits).unwrap();
+            let pub_key = priv_key.to_public_key();
+            let hashed = Sha256::digest(b"integer");
+            let digest = &mut Sha256::new();
+            let sig = sign(
+                &mut rng,
+                None::<&mut StdRng>,
+                &priv_key,
+                &hashed,
+                None,
+                digest,
+            )
+            .unwrap();
+            verify(&pub_key, &hashed, &sig, &mut Sha256::new()).unwrap();
+
+            let s = BigUint::from_bytes_be(&sig);
+            verify_int::<_, Sha256>(&pub_key, &hashed, &s).unwrap();
+            verify_int::<_, Sha256>(&priv_key, &hashed, &s).unwrap();
+            for wrong in &[&s + 1u32, &s + pub_key.n(), BigUint::from(0u32)] {
+                match verify_int::<_, Sha256>(&pub_key, &hashed, wrong) {
+                    Err(Error::Verification) => {}
+                    other => panic!("unexpected result: {:?}", other),
+                }
+            }
+            assert!(verify_int::<_, Sha256>(&pub_key, &Sha256::digest(b"other"), &s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_message() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let msg = b"verified in one step";
+        let hashed = Sha256::digest(msg);
+        let sig = sign(
+            &mut rng,
+            None::<&mut StdRng>,
+            priv_key,
+            &hashed,
+            None,
+            &mut Sha256::new(),
+        )
+        .unwrap();
+        test_util::assert_rejects_malformed_signatures(&pub_key, &sig, |sig| {
+            verify_message::<Sha256, _>(&pub_key, msg, sig)
+        });
+
+        // The hash of the message is not the message.
+        for wrong in &[&b"another message"[..], &hashed[..]] {
+            match verify_message::<Sha256, _>(&pub_key, wrong, &sig) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_tiny_moduli_do_not_panic() {
+        // Moduli far too small for any signature, down to n = 1 where the
+        // encoded message is empty. Every input must fail, none may panic.
+        let hashed = Sha256::digest(b"tiny");
+        for n in (1u32..300).step_by(2) {
+            let pub_key = RSAPublicKey::new_unchecked(BigUint::from(n), BigUint::from(3u32));
+            for sig in &[vec![], vec![0], vec![1], vec![0, 1], vec![0xff; 3]] {
+                assert!(verify(&pub_key, &hashed, sig, &mut Sha256::new()).is_err());
+                assert!(verify_pss_ex::<_, Sha256>(
+                    &pub_key,
+                    &hashed,
+                    sig,
+                    SaltPolicy::Exactly(!0)
+                )
+                .is_err());
+                assert!(verify_pss_compat::<_, Sha256>(&pub_key, &hashed, sig).is_err());
+                assert!(verify_pss_full::<_, Sha256>(&pub_key, &hashed, sig).is_err());
+                let mut scratch = Vec::new();
+                assert!(
+                    verify_with_scratch::<_, Sha256>(&pub_key, &hashed, sig, &mut scratch).is_err()
+                );
+                let s = BigUint::from_bytes_be(sig);
+                assert!(verify_int::<_, Sha256>(&pub_key, &hashed, &s).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_verify_digest() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let (header, body) = (&b"header"[..], &b"body"[..]);
+        let hashed = Sha256::digest(&[header, body].concat());
+
+        let mut digest = Sha256::new();
+        digest.update(header);
+        digest.update(body);
+        let sig = sign_digest(
+            &mut StdRng::seed_from_u64(0),
+            None::<&mut StdRng>,
+            &priv_key,
+            digest.clone(),
+            None,
+        )
+        .unwrap();
+        let expected = sign(
+            &mut StdRng::seed_from_u64(0),
+            None::<&mut StdRng>,
+            &priv_key,
+            &hashed,
+            None,
+            &mut Sha256::new(),
+        )
+        .unwrap();
+        assert_eq!(sig, expected);
+        verify(&pub_key, &hashed, &sig, &mut Sha256::new()).unwrap();
+        verify_digest(&pub_key, digest, &sig).unwrap();
+
+        let mut verifier = PssVerifier::from_digest(&pub_key, Sha256::new().chain(header));
+        verifier.update(body);
+        verifier.verify(&sig).unwrap();
+
+        assert!(verify_digest(&pub_key, Sha256::new().chain(header), &sig).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_unreduced_signature() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let mut rng = StdRng::seed_from_u64(0);
+        let hashed = Sha1::digest(b"out of range");
+
+        let mut tested = 0;
+        while tested < 4 {
+            let sig = sign(
+                &mut rng,
+                None::<&mut StdRng>,
+                &priv_key,
+                &hashed,
+                None,
+                &mut Sha1::new(),
+            )
+            .unwrap();
+            // Only s + n of the same length as n gets past the length check.
+            let forged = (BigUint::from_bytes_be(&sig) + pub_key.n()).to_bytes_be();
+            if forged.len() != pub_key.size() {
+                continue;
+            }
+            tested += 1;
+
+            verify(&pub_key, &hashed, &sig, &mut Sha1::new()).unwrap();
+            match verify(&pub_key, &hashed, &forged, &mut Sha1::new()) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            match verify_with_scratch::<_, Sha1>(&pub_key, &hashed, &forged, &mut Vec::new()) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_pss_with_salt_out() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024().clone();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"logged signature");
+
+        for &salt_len in [0, 20, 32].iter() {
+            let (sig, salt) =
+                sign_pss_with_salt_out::<Sha256, _, _>(&priv_key, &mut rng, &hashed, salt_len)
+                    .unwrap();
+            assert_eq!(salt.len(), salt_len);
+            pub_key
+                .verify(
+                    PaddingScheme::new_pss::<Sha256, _>(rng.clone()),
+                    &hashed,
+                    &sig,
+                )
+                .unwrap();
+
+            // DB = PS || 0x01 || salt
+            let em_bits = pub_key.n().bits() - 1;
+            let mut em = pub_key
+                .raw_encryption_primitive(&sig, sizes::em_len(em_bits))
+                .unwrap();
+            let (db, _) = unmask_db(&mut em, em_bits, &mut Sha256::new()).unwrap();
+            let (ps, recovered) = db.split_at(db.len() - salt_len);
+            assert_eq!(recovered, &salt[..]);
+            assert_eq!(ps.last(), Some(&0x01));
+        }
+    }
+
+    #[test]
+    fn test_sign_verify_encoded() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"encoded signature");
+
+        let encoded = sign_pss_b64url::<Sha256, _, _>(priv_key, &mut rng, &hashed).unwrap();
+        assert!(!encoded.contains(&['+', '/', '='][..]));
+        verify_pss_b64url::<Sha256, _>(&pub_key, &hashed, &encoded).expect("failed to verify");
+        let sig = base64::decode_config(&encoded, base64::URL_SAFE_NO_PAD).unwrap();
+        verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Exactly(32))
+            .expect("failed to verify");
+
+        // The standard alphabet with padding is not base64url.
+        match verify_pss_b64url::<Sha256, _>(&pub_key, &hashed, &base64::encode(&sig)) {
+            Err(Error::ParseError { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let encoded = sign_pss_hex::<Sha256, _, _>(priv_key, &mut rng, &hashed).unwrap();
+        let sig = hex::decode(&encoded).unwrap();
+        assert_eq!(encoded, hex::encode(&sig));
+        verify_pss_ex::<_, Sha256>(&pub_key, &hashed, &sig, SaltPolicy::Exactly(32))
+            .expect("failed to verify");
+        verify_pss_hex::<Sha256, _>(&pub_key, &hashed, &encoded).expect("failed to verify");
+        verify_pss_hex::<Sha256, _>(&pub_key, &hashed, &encoded.to_uppercase())
+            .expect("failed to verify");
+
+        for bad in &[&encoded[1..], &encoded.replacen(&encoded[..1], "g", 1)] {
+            match verify_pss_hex::<Sha256, _>(&pub_key, &hashed, bad) {
+                Err(Error::ParseError { .. }) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        let other = Sha256::digest(b"other signature");
+        assert!(verify_pss_hex::<Sha256, _>(&pub_key, &other, &encoded).is_err());
+    }
+
+    #[test]
+    fn test_verify_pss_full() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_1024().clone();
+        let pub_key = priv_key.to_public_key();
+        let hashed = Sha256::digest(b"signature from another stack");
+
+        for &salt_len in [0, 20, 32, 62].iter() {
+            let (sig, salt) =
+                sign_pss_with_salt_out::<Sha256, _, _>(&priv_key, &mut rng, &hashed, salt_len)
+                    .unwrap();
+            let details = verify_pss_full::<_, Sha256>(&pub_key, &hashed, &sig).unwrap();
+            assert_eq!(details.salt, salt);
+            assert_eq!(details.salt_len, salt_len);
+
+            // The encoded message is the raw public key operation on the
+            // signature and ends with the trailer field.
+            let em_len = sizes::em_len(pub_key.n().bits() - 1);
+            assert_eq!(
+                details.em,
+                pub_key.raw_encryption_primitive(&sig, em_len).unwrap()
+            );
+            assert_eq!(details.em.last(), Some(&0xbc));
+
+            let mut bad_hashed = hashed;
+            bad_hashed[0] ^= 1;
+            match verify_pss_full::<_, Sha256>(&pub_key, &bad_hashed, &sig) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_salt_reuse_detector() {
+        let priv_key = get_private_key();
+        let pub_key: RSAPublicKey = priv_key.clone().into();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut detector = SaltReuseDetector::<_, Sha1>::new(&pub_key);
+
+        let sign_with = |hashed: &[u8], salt: &[u8]| {
+            sign_pss_with_salt::<StdRng, _>(None, &priv_key, hashed, salt, &mut Sha1::new())
+                .unwrap()
+        };
+        let first = Sha1::digest(b"first").to_vec();
+        let second = Sha1::digest(b"second").to_vec();
+        let salt: Vec<u8> = (0..20).map(|_| rng.gen()).collect();
+
+        assert_eq!(
+            detector.observe(&first, &sign_with(&first, &salt)).unwrap(),
+            None
+        );
+        // The same signature again is not a reuse, nor is a fresh salt.
+        assert_eq!(
+            detector.observe(&first, &sign_with(&first, &salt)).unwrap(),
+            None
+        );
+        assert_eq!(
+            detector
+                .observe(&second, &sign_with(&second, &[7; 20]))
+                .unwrap(),
+            None
+        );
+        // Empty salts are not tracked.
+        assert_eq!(
+            detector.observe(&first, &sign_with(&first, &[])).unwrap(),
+            None
+        );
+        assert_eq!(
+            detector.observe(&second, &sign_with(&second, &[])).unwrap(),
+            None
+        );
+        assert_eq!(detector.len(), 2);
+
+        let reuse = detector
+            .observe(&second, &sign_with(&second, &salt))
+            .unwrap();
+        assert_eq!(
+            reuse,
+            Some(SaltReuse {
+                salt,
+                first_hashed: first.clone(),
+                hashed: second.clone(),
+            })
+        );
+
+        // Invalid signatures are rejected and not recorded.
+        let mut sig = sign_with(&first, &[9; 20]);
+        sig[10] ^= 1;
+        assert!(detector.observe(&first, &sig).is_err());
+        assert_eq!(detector.len(), 2);
+    }
 }
@@ -5,7 +5,7 @@ use alloc::vec::Vec;
 
 use crate::errors::{Error, Result};
 use crate::internals;
-use crate::key::{RSAPrivateKey, RSAPublicKey};
+use crate::key::{PublicKeyParts, RSAPrivateKey, RSAPublicKey};
 
 pub trait EncryptionPrimitive {
     /// Do NOT use directly! Only for implementors.
@@ -24,15 +24,25 @@ pub trait DecryptionPrimitive {
 
 impl EncryptionPrimitive for RSAPublicKey {
     fn raw_encryption_primitive(&self, plaintext: &[u8], pad_size: usize) -> Result<Vec<u8>> {
+        // RSAEP and RSAVP1 reject representatives that are not smaller than n,
+        // which would otherwise be silently reduced, so that a signature s
+        // and s + n cannot both verify.
+        if !bool::from(internals::ct_less_than(plaintext, self.n())) {
+            return Err(Error::Verification);
+        }
+
         let mut m = BigUint::from_bytes_be(plaintext);
-        let mut c = internals::encrypt(self, &m);
+        let mut c = match self.barrett() {
+            Some(barrett) => internals::encrypt_with(barrett, self, &m),
+            None => internals::encrypt(self, &m),
+        };
         let mut c_bytes = c.to_bytes_be();
-        let ciphertext = internals::left_pad(&c_bytes, pad_size);
-
-        if pad_size < ciphertext.len() {
+        if pad_size < c_bytes.len() {
             return Err(Error::Verification);
         }
 
+        let ciphertext = internals::left_pad(&c_bytes, pad_size);
+
         // clear out tmp values
         m.zeroize();
         c.zeroize();
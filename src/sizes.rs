@@ -0,0 +1,77 @@
+//! Byte and bit lengths derived from the modulus size.
+//!
+//! Every length used for padding is computed here, so that the rounding and
+//! the guards against underflow and overflow are written only once.
+
+use crate::errors::{Error, Result};
+
+/// Returns the length in bytes of a modulus of `n_bits` bits, which is also
+/// the length of raw signatures and ciphertexts.
+pub(crate) fn key_byte_len(n_bits: usize) -> usize {
+    bytes_for_bits(n_bits)
+}
+
+/// Returns the maximal bit length of a PSS encoded message for a modulus of
+/// `n_bits` bits, RFC 8017 section 8.1.1, failing with `Error::KeyTooSmall`
+/// for an empty modulus instead of underflowing.
+pub(crate) fn em_bits(n_bits: usize) -> Result<usize> {
+    n_bits.checked_sub(1).ok_or(Error::KeyTooSmall)
+}
+
+/// Returns the length in bytes of an encoded message of `em_bits` bits.
+///
+/// This is one byte less than [`key_byte_len`] when the modulus bit length
+/// is one more than a multiple of 8.
+pub(crate) fn em_len(em_bits: usize) -> usize {
+    bytes_for_bits(em_bits)
+}
+
+/// `ceil(bits / 8)`, without the overflow of `(bits + 7) / 8`.
+fn bytes_for_bits(bits: usize) -> usize {
+    bits / 8 + usize::from(bits & 7 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_byte_len() {
+        assert_eq!(key_byte_len(0), 0);
+        assert_eq!(key_byte_len(1), 1);
+        assert_eq!(key_byte_len(8), 1);
+        assert_eq!(key_byte_len(9), 2);
+        assert_eq!(key_byte_len(2047), 256);
+        assert_eq!(key_byte_len(2048), 256);
+        assert_eq!(key_byte_len(2049), 257);
+        let max = !0usize;
+        assert_eq!(key_byte_len(max), max / 8 + 1);
+    }
+
+    #[test]
+    fn test_em_bits() {
+        match em_bits(0) {
+            Err(Error::KeyTooSmall) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(em_bits(1).unwrap(), 0);
+        assert_eq!(em_bits(2048).unwrap(), 2047);
+        assert_eq!(em_bits(2049).unwrap(), 2048);
+    }
+
+    #[test]
+    fn test_em_len() {
+        assert_eq!(em_len(0), 0);
+        assert_eq!(em_len(7), 1);
+        assert_eq!(em_len(8), 1);
+
+        // The encoded message of a 2049 bit modulus fits in 256 bytes, one
+        // less than the modulus itself.
+        for &n_bits in [1025, 2049, 4097].iter() {
+            assert_eq!(em_len(em_bits(n_bits).unwrap()), key_byte_len(n_bits) - 1);
+        }
+        for &n_bits in [1024, 2048, 2050, 4096].iter() {
+            assert_eq!(em_len(em_bits(n_bits).unwrap()), key_byte_len(n_bits));
+        }
+    }
+}
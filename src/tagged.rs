@@ -0,0 +1,303 @@
+//! Signatures that carry the parameters they were made with.
+//!
+//! A [`SelfDescribingSignature`] is encoded as a five byte header followed by
+//! the raw signature:
+//!
+//! ```text
+//! offset  length  field
+//! 0       1       format version, currently 0x01
+//! 1       1       hash:    0x01 SHA-224, 0x02 SHA-256, 0x03 SHA-384, 0x04 SHA-512
+//! 2       1       padding: 0x01 PKCS#1 v1.5, 0x02 PSS
+//! 3       2       salt length in bytes, big-endian, always 0 for PKCS#1 v1.5
+//! 5       k       signature, as long as the modulus
+//! ```
+//!
+//! [`verify_tagged`] reads the header, hashes the message with the hash it
+//! names and checks the signature with the named padding. For PSS the salt
+//! must have exactly the recorded length.
+//!
+//! The header is not covered by the signature. It only tells the verifier how
+//! to check it, so anything that accepts a tagged signature should also
+//! restrict which hashes and paddings it is willing to accept.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use digest::DynDigest;
+use rand::Rng;
+
+use crate::errors::{Error, Result};
+use crate::hash::{new_digest, Hash};
+use crate::key::{PublicKey, RSAPrivateKey};
+use crate::{pkcs1v15, pss};
+
+/// Version byte of the header format described in the module documentation.
+const FORMAT_VERSION: u8 = 0x01;
+
+/// Length in bytes of the header in front of the raw signature.
+pub const HEADER_LEN: usize = 5;
+
+/// The padding scheme of a [`SelfDescribingSignature`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaggedPadding {
+    /// RSASSA-PKCS1-v1_5.
+    Pkcs1v15,
+    /// RSASSA-PSS with a salt of the given length in bytes.
+    Pss { salt_len: usize },
+}
+
+impl TaggedPadding {
+    fn id(self) -> u8 {
+        match self {
+            TaggedPadding::Pkcs1v15 => 0x01,
+            TaggedPadding::Pss { .. } => 0x02,
+        }
+    }
+
+    fn salt_len(self) -> usize {
+        match self {
+            TaggedPadding::Pkcs1v15 => 0,
+            TaggedPadding::Pss { salt_len } => salt_len,
+        }
+    }
+}
+
+/// A signature together with the hash and padding it was made with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfDescribingSignature {
+    hash: Hash,
+    padding: TaggedPadding,
+    signature: Vec<u8>,
+}
+
+impl SelfDescribingSignature {
+    /// The hash function the message was hashed with.
+    pub fn hash(&self) -> Hash {
+        self.hash
+    }
+
+    /// The padding scheme of the signature.
+    pub fn padding(&self) -> TaggedPadding {
+        self.padding
+    }
+
+    /// The raw signature, without the header.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Encodes the header and the signature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let salt_len = self.padding.salt_len() as u16;
+        let mut out = Vec::with_capacity(HEADER_LEN + self.signature.len());
+        out.push(FORMAT_VERSION);
+        out.push(hash_id(self.hash).expect("only supported hashes are stored"));
+        out.push(self.padding.id());
+        out.extend_from_slice(&salt_len.to_be_bytes());
+        out.extend_from_slice(&self.signature);
+        out
+    }
+
+    /// Decodes a header and signature written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Fails with `Error::Verification` on an unknown version, hash or
+    /// padding, or a PKCS#1 v1.5 header with a non-zero salt length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN || bytes[0] != FORMAT_VERSION {
+            return Err(Error::Verification);
+        }
+
+        let hash = hash_from_id(bytes[1])?;
+        let salt_len = u16::from_be_bytes([bytes[3], bytes[4]]) as usize;
+        let padding = match bytes[2] {
+            0x01 if salt_len == 0 => TaggedPadding::Pkcs1v15,
+            0x02 => TaggedPadding::Pss { salt_len },
+            _ => return Err(Error::Verification),
+        };
+
+        Ok(SelfDescribingSignature {
+            hash,
+            padding,
+            signature: bytes[HEADER_LEN..].to_vec(),
+        })
+    }
+}
+
+/// Hashes `msg` with `hash` and signs it with `padding`, using `rng` for the
+/// PSS salt and for blinding.
+///
+/// Only SHA-224, SHA-256, SHA-384 and SHA-512 are supported, any other hash
+/// fails with `Error::InvalidPaddingScheme`, as does a PSS salt length that
+/// does not fit the two byte header field.
+pub fn sign_tagged<R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    hash: Hash,
+    padding: TaggedPadding,
+    msg: &[u8],
+) -> Result<SelfDescribingSignature> {
+    let mut digest = new_digest(hash)?;
+    let hashed = hash_message(&mut *digest, msg);
+
+    let signature = match padding {
+        TaggedPadding::Pkcs1v15 => pkcs1v15::sign(Some(rng), priv_key, Some(&hash), &hashed)?,
+        TaggedPadding::Pss { salt_len } => {
+            if salt_len > 0xffff {
+                return Err(Error::InvalidPaddingScheme);
+            }
+            let mut salt = vec![0; salt_len];
+            rng.fill(&mut salt[..]);
+            pss::sign_pss_with_salt(Some(rng), priv_key, &hashed, &salt, &mut *digest)?
+        }
+    };
+
+    Ok(SelfDescribingSignature {
+        hash,
+        padding,
+        signature,
+    })
+}
+
+/// Verifies a signature produced by [`sign_tagged`] and encoded with
+/// [`SelfDescribingSignature::to_bytes`] over `msg`, using the hash and
+/// padding named in its header.
+pub fn verify_tagged<PK: PublicKey>(pub_key: &PK, msg: &[u8], tagged: &[u8]) -> Result<()> {
+    let sig = SelfDescribingSignature::from_bytes(tagged)?;
+    pss::check_sig_len(pub_key, &sig.signature)?;
+
+    let mut digest = new_digest(sig.hash)?;
+    let hashed = hash_message(&mut *digest, msg);
+
+    match sig.padding {
+        TaggedPadding::Pkcs1v15 => {
+            pkcs1v15::verify(pub_key, Some(&sig.hash), &hashed, &sig.signature)
+        }
+        TaggedPadding::Pss { salt_len } => pss::verify_salt_len(
+            pub_key,
+            &hashed,
+            &sig.signature,
+            Some(salt_len),
+            &mut *digest,
+        )
+        .map(|_| ()),
+    }
+}
+
+fn hash_id(hash: Hash) -> Result<u8> {
+    match hash {
+        Hash::SHA2_224 => Ok(0x01),
+        Hash::SHA2_256 => Ok(0x02),
+        Hash::SHA2_384 => Ok(0x03),
+        Hash::SHA2_512 => Ok(0x04),
+        _ => Err(Error::InvalidPaddingScheme),
+    }
+}
+
+fn hash_from_id(id: u8) -> Result<Hash> {
+    match id {
+        0x01 => Ok(Hash::SHA2_224),
+        0x02 => Ok(Hash::SHA2_256),
+        0x03 => Ok(Hash::SHA2_384),
+        0x04 => Ok(Hash::SHA2_512),
+        _ => Err(Error::Verification),
+    }
+}
+
+fn hash_message(digest: &mut dyn DynDigest, msg: &[u8]) -> Vec<u8> {
+    digest.update(msg);
+    digest.finalize_reset().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::PublicKeyParts;
+    use crate::test_util;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const HASHES: [Hash; 4] = [
+        Hash::SHA2_224,
+        Hash::SHA2_256,
+        Hash::SHA2_384,
+        Hash::SHA2_512,
+    ];
+
+    #[test]
+    fn test_sign_verify_tagged() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_2048().clone();
+        let pub_key = priv_key.to_public_key();
+        let msg = b"stored in a database column";
+
+        for &hash in HASHES.iter() {
+            for &padding in [
+                TaggedPadding::Pkcs1v15,
+                TaggedPadding::Pss { salt_len: 0 },
+                TaggedPadding::Pss { salt_len: 32 },
+            ]
+            .iter()
+            {
+                let sig = sign_tagged(&mut rng, &priv_key, hash, padding, msg).unwrap();
+                let bytes = sig.to_bytes();
+                assert_eq!(bytes.len(), HEADER_LEN + pub_key.size());
+                assert_eq!(bytes[0], FORMAT_VERSION);
+                assert_eq!(SelfDescribingSignature::from_bytes(&bytes).unwrap(), sig);
+
+                verify_tagged(&pub_key, msg, &bytes).unwrap();
+                assert!(verify_tagged(&pub_key, b"another message", &bytes).is_err());
+
+                // Pointing the header at another hash or salt length breaks it.
+                for &other in HASHES.iter().filter(|&&other| other != hash) {
+                    let mut forged = bytes.clone();
+                    forged[1] = hash_id(other).unwrap();
+                    assert!(verify_tagged(&pub_key, msg, &forged).is_err());
+                }
+                let mut forged = bytes.clone();
+                forged[4] ^= 1;
+                assert!(verify_tagged(&pub_key, msg, &forged).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_format() {
+        let sig = SelfDescribingSignature {
+            hash: Hash::SHA2_384,
+            padding: TaggedPadding::Pss { salt_len: 0x0130 },
+            signature: vec![0xaa, 0xbb],
+        };
+        assert_eq!(sig.to_bytes(), [0x01, 0x03, 0x02, 0x01, 0x30, 0xaa, 0xbb]);
+
+        for bytes in [
+            &[0x01, 0x02, 0x01, 0x00][..],
+            &[0x02, 0x02, 0x01, 0x00, 0x00, 0xaa],
+            &[0x01, 0x05, 0x01, 0x00, 0x00, 0xaa],
+            &[0x01, 0x02, 0x03, 0x00, 0x00, 0xaa],
+            &[0x01, 0x02, 0x01, 0x00, 0x20, 0xaa],
+        ]
+        .iter()
+        {
+            match SelfDescribingSignature::from_bytes(bytes) {
+                Err(Error::Verification) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_hash() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = RSAPrivateKey::new(&mut rng, 512).unwrap();
+        match sign_tagged(
+            &mut rng,
+            &priv_key,
+            Hash::SHA1,
+            TaggedPadding::Pkcs1v15,
+            b"msg",
+        ) {
+            Err(Error::InvalidPaddingScheme) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Besides [`test_rng`] this module provides fixed private keys, so that
+//! test suites do not have to generate fresh keys in every test. Everything
+//! here is public knowledge and must never protect anything.
+
+use alloc::vec;
+#[cfg(feature = "timing-tests")]
+use alloc::vec::Vec;
+
+use num_bigint::BigUint;
+#[cfg(feature = "test-util")]
+use rand::{CryptoRng, Rng, SeedableRng};
+#[cfg(feature = "test-util")]
+use rand_chacha::ChaCha20Rng;
+
+use crate::key::RSAPrivateKey;
+
+/// Returns a deterministic random number generator for reproducible tests.
+///
+/// The generator is ChaCha20 seeded from `seed`, so the same seed yields the
+/// same salts, blinding factors and keys on every run and platform, within a
+/// release of this crate. Changes to how the crate draws randomness may
+/// change them in a later release.
+///
+/// **Never use this outside of tests.** Everything derived from it can be
+/// recomputed by anyone who knows or guesses the seed.
+///
+/// # Example
+///
+/// ```
+/// use rsa::{test_util::test_rng, PaddingScheme, RSAPrivateKey};
+/// use sha2::{Digest, Sha256};
+///
+/// let key = RSAPrivateKey::new(&mut test_rng(0), 512).unwrap();
+/// let digest = Sha256::digest(b"test");
+///
+/// let sign = |seed| {
+///     let padding = PaddingScheme::new_pss::<Sha256, _>(test_rng(seed));
+///     key.sign(padding, &digest).unwrap()
+/// };
+/// assert_eq!(sign(1), sign(1));
+/// assert_ne!(sign(1), sign(2));
+/// ```
+#[cfg(feature = "test-util")]
+pub fn test_rng(seed: u64) -> impl Rng + CryptoRng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// Returns a fixed 1024 bit private key with public exponent 65537.
+///
+/// **Never use this outside of tests.** The key is published in the source
+/// of this crate, so anyone can forge its signatures and decrypt what is
+/// encrypted to it.
+///
+/// # Example
+///
+/// ```
+/// use rsa::{test_util::test_key_1024, PaddingScheme, PublicKey, PublicKeyParts};
+///
+/// let key = test_key_1024();
+/// assert_eq!(key.n().bits(), 1024);
+/// let sig = key.sign(PaddingScheme::new_pkcs1v15_sign(None), b"test").unwrap();
+/// key.verify(PaddingScheme::new_pkcs1v15_sign(None), b"test", &sig).unwrap();
+/// ```
+pub fn test_key_1024() -> &'static RSAPrivateKey {
+    &TEST_KEY_1024
+}
+
+/// Returns a fixed 2048 bit private key with public exponent 65537.
+///
+/// **Never use this outside of tests**, for the same reasons as
+/// [`test_key_1024`].
+pub fn test_key_2048() -> &'static RSAPrivateKey {
+    &TEST_KEY_2048
+}
+
+/// Asserts that `verify` accepts `sig`, a valid signature by `pub_key`, and
+/// fails with `Error::Verification` for corrupted, truncated, overlong and
+/// out of range forms of it.
+#[cfg(test)]
+pub(crate) fn assert_rejects_malformed_signatures<K, F>(pub_key: &K, sig: &[u8], verify: F)
+where
+    K: crate::PublicKeyParts,
+    F: Fn(&[u8]) -> crate::errors::Result<()>,
+{
+    verify(sig).expect("failed to verify");
+
+    let modulus = pub_key.n().to_bytes_be();
+    let mut longer = vec![0];
+    longer.extend_from_slice(sig);
+    let mut flipped = sig.to_vec();
+    flipped[sig.len() / 2] ^= 1;
+    for bad in &[&sig[1..], &longer[..], &modulus[..], &flipped[..], &[][..]] {
+        match verify(bad) {
+            Err(crate::errors::Error::Verification) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+/// Runs `a` and `b` `samples` times each in random order and returns
+/// Welch's t statistic of their running times, a dudect style check for
+/// timing differences between two classes of inputs.
+///
+/// The slowest tenth of all runs is dropped first, as those are mostly
+/// interrupts and cache misses. Values near zero mean the timings could
+/// not be told apart, dudect treats more than 10 as a certain leak.
+/// `a` and `b` should take at least a few microseconds, shorter runs mostly
+/// measure the resolution of the clock.
+///
+/// This is a coarse, best-effort statistical test, not a proof of constant
+/// time behavior: the compiler, the machine and the load on it all change
+/// the outcome, so it is only built with the opt-in `timing-tests` feature.
+#[cfg(feature = "timing-tests")]
+pub fn timing_t_statistic<A, B, RA, RB>(samples: usize, mut a: A, mut b: B) -> f64
+where
+    A: FnMut() -> RA,
+    B: FnMut() -> RB,
+{
+    use std::time::Instant;
+
+    let mut rng = test_rng(0);
+    let mut timings = [Vec::with_capacity(samples), Vec::with_capacity(samples)];
+    while timings[0].len() < samples || timings[1].len() < samples {
+        let class = if timings[0].len() == samples {
+            1
+        } else if timings[1].len() == samples {
+            0
+        } else {
+            rng.gen_range(0, 2)
+        };
+        let start = Instant::now();
+        if class == 0 {
+            let _ = a();
+        } else {
+            let _ = b();
+        }
+        timings[class].push(start.elapsed().as_nanos() as f64);
+    }
+
+    let mut all: Vec<f64> = timings.iter().flatten().cloned().collect();
+    all.sort_by(|x, y| x.partial_cmp(y).expect("durations are not NaN"));
+    let cutoff = all[all.len() * 9 / 10];
+
+    let stats = |times: &[f64]| {
+        let kept: Vec<f64> = times.iter().cloned().filter(|&t| t <= cutoff).collect();
+        let n = kept.len() as f64;
+        let mean = kept.iter().sum::<f64>() / n;
+        let var = kept.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / (n - 1.0);
+        (mean, var, n)
+    };
+    let (mean_a, var_a, n_a) = stats(&timings[0]);
+    let (mean_b, var_b, n_b) = stats(&timings[1]);
+    let denom = (var_a / n_a + var_b / n_b).sqrt();
+    if denom == 0.0 {
+        return 0.0;
+    }
+    (mean_a - mean_b) / denom
+}
+
+/// Panics if [`timing_t_statistic`] tells the running times of `a` and `b`
+/// apart by more than `threshold`.
+///
+/// Only built with the `timing-tests` feature, as it fails spuriously on a
+/// busy machine. A failure on a quiet machine is worth looking into, a pass
+/// does not prove the absence of a leak.
+#[cfg(feature = "timing-tests")]
+pub fn assert_timing_indistinguishable<A, B, RA, RB>(samples: usize, threshold: f64, a: A, b: B)
+where
+    A: FnMut() -> RA,
+    B: FnMut() -> RB,
+{
+    let t = timing_t_statistic(samples, a, b);
+    assert!(
+        t.abs() <= threshold,
+        "timings differ: |t| = {:.2} > {:.2}",
+        t.abs(),
+        threshold
+    );
+}
+
+lazy_static! {
+    static ref TEST_KEY_1024: RSAPrivateKey = fixed_key(N_1024, D_1024, P_1024, Q_1024);
+    static ref TEST_KEY_2048: RSAPrivateKey = fixed_key(N_2048, D_2048, P_2048, Q_2048);
+}
+
+fn fixed_key(n: &str, d: &str, p: &str, q: &str) -> RSAPrivateKey {
+    let parse = |hex: &str| BigUint::parse_bytes(hex.as_bytes(), 16).expect("valid hex");
+    let mut key = RSAPrivateKey::from_components(
+        parse(n),
+        65537u32.into(),
+        parse(d),
+        vec![parse(p), parse(q)],
+    );
+    key.precompute().expect("valid test key");
+    key
+}
+
+// Components of the key returned by [`test_key_1024`], in hexadecimal.
+const N_1024: &str = concat!(
+    "ad1358737a233d23da2f5e3059f5412f1167771c57b913a59f896ba9d440c3b5",
+    "fe0def1cc2d89000d800d11ab1c0a6cc145de5aa1580320e68f2903f7ad0f256",
+    "38c8f8c1e16721734bf2b539cf08831e0b28dd36df2cf6dfc7028d439e20f77c",
+    "1c1939951f5eedf6d4b8191349841e06781d9b65f2e6e8311314dd709dff59f9",
+);
+const D_1024: &str = concat!(
+    "83b00987ebb1ae4e7e2fb661e1c09b353defd4902b2af608f9e76c94ce9bec8d",
+    "a7dd604728f5f033c42672f3f5b598f404b7210d15c8f1cd470af0b86c315981",
+    "02e593f7493abb68f8c48a4b31740958c3db5a06faa5017f8c13952ec32c2b1e",
+    "d980f59f0148f46ca4a42869702a8960cb5e3269bd857f8baec266b4ee5d3695",
+);
+const P_1024: &str = concat!(
+    "d41ed0e45216bc6e0ef8f56f58a557c4f09755fd4c7cbf0599ce9bcf5f72596d",
+    "cbe4d4af8a68cee3878a6b96c5173503bb2dc378ab62ecb52f230fce630eeb7f",
+);
+const Q_1024: &str = concat!(
+    "d0e0d8f2cd801afde014b39eaea34ec480cc67689dc31d7cae4daedb1995cdcf",
+    "9d8978ca6b4a41b0a0d9e33d709009d5bc648ada3f9bb20086fc3b9d5e3bd687",
+);
+
+// Components of the key returned by [`test_key_2048`], in hexadecimal.
+const N_2048: &str = concat!(
+    "cc92e2ac51d7cf7d801d9af7fb028059848a87aa749d786f0b6bc4f42c75602a",
+    "587055d69fa7cae5fb075fba6786dec850c14e222dbde3939b335820b430eece",
+    "15458908060922c768fbe104d41d6a1484eeffc1e1e25d3a8fff529bd9ca3504",
+    "83039db3d43387048ce43b6e62aa498d2dc5113d53b44685b485c0b79410f19f",
+    "b96530205987b1b73a49d42c8ca55fe5cb9ea5713ade8a308032d85cb49086d2",
+    "5a640f602461810d87c308fb269ae95a290ad761aca8f1af12efcef1b77aafb4",
+    "4523cc285c23a6cd8e4c246ca75b50e9ea0b1d009e21dbd1f4e1812488f2d015",
+    "cd02d0ead93b52ce2006c20ef238fdd617d352c686afe6f9864b5b2508d56231",
+);
+const D_2048: &str = concat!(
+    "2dba8e30d06773c0939c70f470edfe3604710c88d42edf1026aa36d61133ab9e",
+    "986650c9c90ce5adf628c303ae60a45f4914533610a9e821f6a296bd5256eaa4",
+    "4cdcf7e4a674bdf17bb305467bd6fdcc3ed14780d5d0971339c36c4710ae5c38",
+    "5f90e8f603593481fcafbb19381b4bc8e32be39b71d420d5e8d7b05c8c1c8a4c",
+    "638b3da4c9d5609ff666db98d32c85207677fe2458e80b113550cbe095d81807",
+    "f6cbca840f9b9f1c3ef2908512acd79f0dfc25e2efb06111e5eb57a4c4ca8f1a",
+    "87cb4c4c41cef5cc7b304e7cbd814e185478d525dc51933f1dd7902636b8160f",
+    "fe2bb64d9dba7925f641237916392be0aa8accca51a8606055f0e203cf32e61",
+);
+const P_2048: &str = concat!(
+    "ed1cf9479a25bf98e06149f5dd04579ced90bc4d9b4b4f4da8bbe8eb37eeb2d7",
+    "38146b6eb7f36c68647c48795141cb4b7ce8807594ecbe6bc845012c06e95a87",
+    "dcff1688fdc2cd736dbc4c44c3a6c914a05957e169412a33dcf5dba47ad6d478",
+    "a447bb9497025213c8b03e640d2bc70602458484905e085e6a2e6931c0b9e145",
+);
+const Q_2048: &str = concat!(
+    "dcde64c193faa5b1e980a988bdd9de90bec2650256ffa507122528f5dfd98154",
+    "ae8c3bfdb77acbfad730c8eded149a8cd746acf14ec20efcb5f820965509febe",
+    "a0be790a99835491143430d70b37f67108f9a059814d3dcbc2c0022456b90166",
+    "73a054ddcb1598eef8d25170d9368ed37eb4c3ef5711ce594b3b64e51ce04dfd",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PublicKeyParts;
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_rng_is_deterministic() {
+        let mut a = [0u8; 64];
+        let mut b = [0u8; 64];
+        test_rng(42).fill(&mut a[..]);
+        test_rng(42).fill(&mut b[..]);
+        assert_eq!(a[..], b[..]);
+
+        test_rng(43).fill(&mut b[..]);
+        assert_ne!(a[..], b[..]);
+    }
+
+    #[test]
+    fn test_fixed_keys() {
+        for &(key, bits) in [(test_key_1024(), 1024), (test_key_2048(), 2048)].iter() {
+            assert_eq!(key.n().bits(), bits);
+            key.validate().unwrap();
+        }
+    }
+}
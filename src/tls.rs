@@ -0,0 +1,137 @@
+//! RSASSA-PSS with the parameters of the TLS 1.3 signature schemes.
+//!
+//! TLS 1.3, RFC 8446 section 4.2.3, requires PSS signatures to use MGF1 with
+//! the same hash as the message and a salt as long as the output of that
+//! hash. The `rsa_pss_rsae_*` schemes make such signatures with an ordinary
+//! `rsaEncryption` key and the `rsa_pss_pss_*` schemes with a key restricted
+//! to PSS by its `RSASSA-PSS` algorithm identifier. The signatures are the
+//! same, so both use the helpers of this module, only the certificate tells
+//! them apart.
+//!
+//! Verification requires the exact salt length, so signatures that meet the
+//! PSS standard but not the stricter TLS profile are rejected.
+
+use alloc::vec::Vec;
+
+use digest::Digest;
+use rand::Rng;
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::errors::Result;
+use crate::key::{PublicKey, RSAPrivateKey};
+use crate::pss;
+
+/// Signs `msg` for `rsa_pss_rsae_sha256` and `rsa_pss_pss_sha256`, using
+/// `rng` for the salt and for blinding.
+pub fn sign_rsa_pss_sha256<R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    pss::sign_pss_hash_len_salt::<Sha256, _, _>(priv_key, rng, &Sha256::digest(msg))
+}
+
+/// Signs `msg` for `rsa_pss_rsae_sha384` and `rsa_pss_pss_sha384`, using
+/// `rng` for the salt and for blinding.
+pub fn sign_rsa_pss_sha384<R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    pss::sign_pss_hash_len_salt::<Sha384, _, _>(priv_key, rng, &Sha384::digest(msg))
+}
+
+/// Signs `msg` for `rsa_pss_rsae_sha512` and `rsa_pss_pss_sha512`, using
+/// `rng` for the salt and for blinding.
+pub fn sign_rsa_pss_sha512<R: Rng>(
+    rng: &mut R,
+    priv_key: &RSAPrivateKey,
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    pss::sign_pss_hash_len_salt::<Sha512, _, _>(priv_key, rng, &Sha512::digest(msg))
+}
+
+/// Verifies a `rsa_pss_rsae_sha256` or `rsa_pss_pss_sha256` signature of `msg`.
+pub fn verify_rsa_pss_sha256<PK: PublicKey>(pub_key: &PK, msg: &[u8], sig: &[u8]) -> Result<()> {
+    pss::verify_pss_hash_len_salt::<_, Sha256>(pub_key, &Sha256::digest(msg), sig)
+}
+
+/// Verifies a `rsa_pss_rsae_sha384` or `rsa_pss_pss_sha384` signature of `msg`.
+pub fn verify_rsa_pss_sha384<PK: PublicKey>(pub_key: &PK, msg: &[u8], sig: &[u8]) -> Result<()> {
+    pss::verify_pss_hash_len_salt::<_, Sha384>(pub_key, &Sha384::digest(msg), sig)
+}
+
+/// Verifies a `rsa_pss_rsae_sha512` or `rsa_pss_pss_sha512` signature of `msg`.
+pub fn verify_rsa_pss_sha512<PK: PublicKey>(pub_key: &PK, msg: &[u8], sig: &[u8]) -> Result<()> {
+    pss::verify_pss_hash_len_salt::<_, Sha512>(pub_key, &Sha512::digest(msg), sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pss::SaltPolicy;
+    use crate::{test_util, PublicKeyParts};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// The content covered by a server's CertificateVerify signature.
+    fn certificate_verify_content() -> Vec<u8> {
+        let mut content = vec![0x20; 64];
+        content.extend_from_slice(b"TLS 1.3, server CertificateVerify\0");
+        content.extend_from_slice(&Sha256::digest(b"transcript"));
+        content
+    }
+
+    #[test]
+    fn test_tls_pss_parameters() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let priv_key = test_util::test_key_2048().clone();
+        let pub_key = priv_key.to_public_key();
+        let msg = certificate_verify_content();
+
+        type Sign = fn(&mut StdRng, &RSAPrivateKey, &[u8]) -> Result<Vec<u8>>;
+        type Verify = fn(&crate::RSAPublicKey, &[u8], &[u8]) -> Result<()>;
+        let schemes: [(Sign, Verify, usize); 3] = [
+            (sign_rsa_pss_sha256, verify_rsa_pss_sha256, 32),
+            (sign_rsa_pss_sha384, verify_rsa_pss_sha384, 48),
+            (sign_rsa_pss_sha512, verify_rsa_pss_sha512, 64),
+        ];
+
+        for (i, &(sign, verify, h_len)) in schemes.iter().enumerate() {
+            let sig = sign(&mut rng, &priv_key, &msg).unwrap();
+            assert_eq!(sig.len(), pub_key.size());
+            verify(&pub_key, &msg, &sig).unwrap();
+            assert!(verify(&pub_key, b"another transcript", &sig).is_err());
+
+            // Recovering the salt unmasks the data block with MGF1 over the
+            // message hash, so this checks both the MGF and the salt length.
+            let s_len = match h_len {
+                32 => pss::verify_pss_ex::<_, Sha256>(
+                    &pub_key,
+                    &Sha256::digest(&msg),
+                    &sig,
+                    SaltPolicy::Any,
+                ),
+                48 => pss::verify_pss_ex::<_, Sha384>(
+                    &pub_key,
+                    &Sha384::digest(&msg),
+                    &sig,
+                    SaltPolicy::Any,
+                ),
+                _ => pss::verify_pss_ex::<_, Sha512>(
+                    &pub_key,
+                    &Sha512::digest(&msg),
+                    &sig,
+                    SaltPolicy::Any,
+                ),
+            };
+            assert_eq!(s_len.unwrap(), h_len);
+
+            for (j, &(_, other_verify, _)) in schemes.iter().enumerate() {
+                if i != j {
+                    assert!(other_verify(&pub_key, &msg, &sig).is_err());
+                }
+            }
+        }
+    }
+}
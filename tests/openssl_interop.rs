@@ -0,0 +1,278 @@
+//! Signature interoperability with the `openssl` command line tool.
+//!
+//! Enabled with the `test-openssl` feature. Every test is skipped if no
+//! `openssl` binary is found on the `PATH`.
+#![cfg(feature = "test-openssl")]
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use digest::{Digest, DynDigest};
+use rand::{rngs::StdRng, SeedableRng};
+use rsa::{
+    Hash, PaddingScheme, PrivateKeyPemEncoding, PublicKey, PublicKeyPemEncoding, RSAPrivateKey,
+    RSAPublicKey,
+};
+use sha2::{Sha256, Sha384, Sha512};
+
+const MESSAGE: &[u8] = b"interoperability test message\n";
+
+/// A scratch directory holding an OpenSSL generated key, removed on drop.
+struct Fixture {
+    dir: PathBuf,
+    priv_key: RSAPrivateKey,
+    pub_key: RSAPublicKey,
+}
+
+impl Fixture {
+    /// Returns `None` if `openssl` is not available.
+    fn new(name: &str) -> Option<Fixture> {
+        if Command::new("openssl").arg("version").output().is_err() {
+            eprintln!("openssl not found, skipping {}", name);
+            return None;
+        }
+
+        let dir = std::env::temp_dir().join(format!("rsa-interop-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let key_path = dir.join("key.pem");
+        openssl(&["genrsa", "-out", path(&key_path), "2048"]);
+        let pem = rsa::pem::parse(fs::read(&key_path).unwrap()).unwrap();
+        let priv_key = RSAPrivateKey::try_from(pem).expect("failed to parse OpenSSL key");
+        priv_key.validate().unwrap();
+        let pub_key = priv_key.to_public_key();
+
+        fs::write(dir.join("pub.pem"), pub_key.to_pem_pkcs8().unwrap()).unwrap();
+        fs::write(dir.join("msg"), MESSAGE).unwrap();
+
+        Some(Fixture {
+            dir,
+            priv_key,
+            pub_key,
+        })
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Signs `MESSAGE` with `openssl dgst` and returns the signature.
+    fn openssl_sign(&self, hash: &str, sigopts: &[&str]) -> Vec<u8> {
+        let sig_path = self.file("openssl.sig");
+        let mut args = vec![
+            "dgst".to_string(),
+            format!("-{}", hash),
+            "-sign".to_string(),
+            path(&self.file("key.pem")).to_string(),
+            "-out".to_string(),
+            path(&sig_path).to_string(),
+        ];
+        for opt in sigopts {
+            args.push("-sigopt".to_string());
+            args.push(opt.to_string());
+        }
+        args.push(path(&self.file("msg")).to_string());
+
+        openssl(&args.iter().map(String::as_str).collect::<Vec<_>>());
+        fs::read(&sig_path).unwrap()
+    }
+
+    /// Verifies `sig` over `MESSAGE` with `openssl dgst`.
+    fn openssl_verify(&self, hash: &str, sigopts: &[&str], sig: &[u8]) -> bool {
+        let sig_path = self.file("rust.sig");
+        fs::write(&sig_path, sig).unwrap();
+
+        let mut command = Command::new("openssl");
+        command
+            .arg("dgst")
+            .arg(format!("-{}", hash))
+            .arg("-verify")
+            .arg(self.file("pub.pem"))
+            .arg("-signature")
+            .arg(&sig_path);
+        for opt in sigopts {
+            command.arg("-sigopt").arg(opt);
+        }
+        command.arg(self.file("msg"));
+
+        let output = command.output().unwrap();
+        if !output.status.success() {
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+        }
+        output.status.success()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+fn openssl(args: &[&str]) {
+    let output = Command::new("openssl").args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "openssl {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Runs `test` for every hash with its `openssl dgst` name.
+fn for_each_hash(
+    mut test: impl FnMut(&str, Hash, Vec<u8>, fn() -> PaddingScheme, fn(usize) -> PaddingScheme),
+) {
+    fn pss<D: 'static + Digest + DynDigest>() -> PaddingScheme {
+        PaddingScheme::new_pss::<D, _>(StdRng::seed_from_u64(0))
+    }
+    fn pss_with_salt<D: 'static + Digest + DynDigest>(len: usize) -> PaddingScheme {
+        PaddingScheme::new_pss_with_salt::<D, _>(StdRng::seed_from_u64(0), len)
+    }
+
+    test(
+        "sha256",
+        Hash::SHA2_256,
+        Sha256::digest(MESSAGE).to_vec(),
+        pss::<Sha256>,
+        pss_with_salt::<Sha256>,
+    );
+    test(
+        "sha384",
+        Hash::SHA2_384,
+        Sha384::digest(MESSAGE).to_vec(),
+        pss::<Sha384>,
+        pss_with_salt::<Sha384>,
+    );
+    test(
+        "sha512",
+        Hash::SHA2_512,
+        Sha512::digest(MESSAGE).to_vec(),
+        pss::<Sha512>,
+        pss_with_salt::<Sha512>,
+    );
+}
+
+#[test]
+fn openssl_pss_signatures_verify() {
+    let fixture = match Fixture::new("pss-verify") {
+        Some(fixture) => fixture,
+        None => return,
+    };
+
+    for_each_hash(|name, _, hashed, pss, _| {
+        // -1 is a salt as long as the digest, -2 the maximal one.
+        for saltlen in &["-1", "-2", "0", "17"] {
+            let saltopt = format!("rsa_pss_saltlen:{}", saltlen);
+            let sig = fixture.openssl_sign(name, &["rsa_padding_mode:pss", &saltopt]);
+            fixture
+                .pub_key
+                .verify(pss(), &hashed, &sig)
+                .unwrap_or_else(|err| panic!("{} with salt length {}: {:?}", name, saltlen, err));
+        }
+    });
+}
+
+#[test]
+fn pss_signatures_verify_in_openssl() {
+    let fixture = match Fixture::new("pss-sign") {
+        Some(fixture) => fixture,
+        None => return,
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for_each_hash(|name, _, hashed, pss, pss_with_salt| {
+        // The default salt is the largest that fits, which OpenSSL detects.
+        let sig = fixture
+            .priv_key
+            .sign_blinded(&mut rng, pss(), &hashed)
+            .unwrap();
+        assert!(fixture.openssl_verify(
+            name,
+            &["rsa_padding_mode:pss", "rsa_pss_saltlen:-2"],
+            &sig
+        ));
+        assert!(fixture.openssl_verify(
+            name,
+            &["rsa_padding_mode:pss", "rsa_pss_saltlen:max"],
+            &sig
+        ));
+
+        // A digest sized salt is what verifiers insisting on a fixed length
+        // usually expect.
+        let sig = fixture
+            .priv_key
+            .sign_blinded(&mut rng, pss_with_salt(hashed.len()), &hashed)
+            .unwrap();
+        assert!(fixture.openssl_verify(
+            name,
+            &["rsa_padding_mode:pss", "rsa_pss_saltlen:-1"],
+            &sig
+        ));
+        assert!(fixture.openssl_verify(
+            name,
+            &["rsa_padding_mode:pss", "rsa_pss_saltlen:-2"],
+            &sig
+        ));
+        assert!(!fixture.openssl_verify(
+            name,
+            &["rsa_padding_mode:pss", "rsa_pss_saltlen:0"],
+            &sig
+        ));
+    });
+}
+
+#[test]
+fn pkcs1v15_signatures_round_trip() {
+    let fixture = match Fixture::new("pkcs1v15") {
+        Some(fixture) => fixture,
+        None => return,
+    };
+
+    for_each_hash(|name, hash, hashed, _, _| {
+        let padding = || PaddingScheme::new_pkcs1v15_sign(Some(hash));
+
+        let sig = fixture.openssl_sign(name, &[]);
+        fixture.pub_key.verify(padding(), &hashed, &sig).unwrap();
+
+        let rust_sig = fixture.priv_key.sign(padding(), &hashed).unwrap();
+        assert_eq!(rust_sig, sig);
+        assert!(fixture.openssl_verify(name, &[], &rust_sig));
+    });
+}
+
+#[test]
+fn private_keys_load_in_openssl() {
+    let fixture = match Fixture::new("private-keys") {
+        Some(fixture) => fixture,
+        None => return,
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let multi_prime = rsa::algorithms::generate_multi_prime_key(&mut rng, 3, 2048).unwrap();
+
+    for (name, key) in &[
+        ("two-prime", &fixture.priv_key),
+        ("three-prime", &multi_prime),
+    ] {
+        for pem in &[key.to_pem_pkcs1().unwrap(), key.to_pem_pkcs8().unwrap()] {
+            let key_path = fixture.file("rust.pem");
+            fs::write(&key_path, pem).unwrap();
+            let output = Command::new("openssl")
+                .args(["rsa", "-check", "-noout", "-in", path(&key_path)])
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}